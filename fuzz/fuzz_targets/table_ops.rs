@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_ht::testing::check_bytes;
+use rust_ht::{IdentityHasher, SimpleHasher};
+
+fuzz_target!(|data: &[u8]| {
+    check_bytes::<SimpleHasher>(data);
+    check_bytes::<IdentityHasher>(data);
+});
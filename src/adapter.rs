@@ -0,0 +1,70 @@
+use crate::{HashTable, HashValue, HashableKey};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StdHashMapAdapter<K: Hash + Eq, V> {
+    map: HashMap<K, V>,
+}
+
+impl<K: Hash + Eq, V> StdHashMapAdapter<K, V> {
+    pub fn new() -> Self {
+        StdHashMapAdapter {
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        StdHashMapAdapter {
+            map: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn into_inner(self) -> HashMap<K, V> {
+        self.map
+    }
+}
+
+impl<K: Hash + Eq, V> From<HashMap<K, V>> for StdHashMapAdapter<K, V> {
+    fn from(map: HashMap<K, V>) -> Self {
+        StdHashMapAdapter { map }
+    }
+}
+
+impl<K: HashableKey + Hash + Eq, V: HashValue> HashTable<K, V> for StdHashMapAdapter<K, V> {
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.map.get_mut(key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.map.remove(key)
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    fn size(&self) -> usize {
+        self.map.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        0
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{
+    grown_capacity, HashTable, HashValue, HashableKey, Hasher, KVPair, DEFAULT_CAPACITY,
+    DEFAULT_GROWTH_FACTOR,
+};
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+
+const MAX_AVERAGE_CHAIN_LENGTH: f64 = 2.0;
+
+// Separate chaining: each bucket is its own Vec of entries, so removal just
+// drops the entry from its chain and there is nothing like a tombstone.
+pub struct ChainedHashTable<K: HashableKey, V: HashValue, H: Hasher<K>> {
+    buckets: Vec<Vec<KVPair<K, V>>>,
+    slots_used: usize,
+    resize_count: usize,
+    ph_1: PhantomData<H>,
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> ChainedHashTable<K, V, H> {
+    fn bucket_of<Q>(&self, key: &Q) -> usize
+    where
+        Q: ?Sized,
+        H: Hasher<Q>,
+    {
+        H::hash(key) % self.buckets.len()
+    }
+
+    fn get_pos<Q>(&self, key: &Q) -> Option<(usize, usize)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let bucket = self.bucket_of(key);
+        self.buckets[bucket]
+            .iter()
+            .position(|kv| kv.key.borrow() == key)
+            .map(|index| (bucket, index))
+    }
+
+    fn grow_if_needed(&mut self) {
+        let current_buckets = self.buckets.len();
+        if (self.slots_used + 1) as f64 <= current_buckets as f64 * MAX_AVERAGE_CHAIN_LENGTH {
+            return;
+        }
+        let next_buckets =
+            grown_capacity(current_buckets, DEFAULT_GROWTH_FACTOR).unwrap_or_else(|| {
+                panic!(
+                    "couldn't resize from {}: capacity overflow",
+                    current_buckets
+                )
+            });
+        let old_buckets = std::mem::take(&mut self.buckets);
+        self.buckets.resize_with(next_buckets, Vec::new);
+        for kv in old_buckets.into_iter().flatten() {
+            let bucket = self.bucket_of(&kv.key);
+            self.buckets[bucket].push(kv);
+        }
+        self.resize_count += 1;
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some((bucket, index)) = self.get_pos(&key) {
+            let kv = &mut self.buckets[bucket][index];
+            return Some(std::mem::replace(&mut kv.value, value));
+        }
+        self.grow_if_needed();
+        let bucket = self.bucket_of(&key);
+        self.buckets[bucket].push(KVPair { key, value });
+        self.slots_used += 1;
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let (bucket, index) = self.get_pos(key)?;
+        Some(&self.buckets[bucket][index].value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let (bucket, index) = self.get_pos(key)?;
+        Some(&mut self.buckets[bucket][index].value)
+    }
+
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.get_pos(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let (bucket, index) = self.get_pos(key)?;
+        self.slots_used -= 1;
+        Some(self.buckets[bucket].swap_remove(index).value)
+    }
+
+    pub fn iter(&self) -> ChainedIter<'_, K, V> {
+        ChainedIter {
+            buckets: self.buckets.iter(),
+            chain: [].iter(),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn resize_count(&self) -> usize {
+        self.resize_count
+    }
+
+    pub fn longest_chain(&self) -> usize {
+        self.buckets.iter().map(Vec::len).max().unwrap_or(0)
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> HashTable<K, V> for ChainedHashTable<K, V, H> {
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        ChainedHashTable::insert(self, key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        ChainedHashTable::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        ChainedHashTable::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        ChainedHashTable::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        ChainedHashTable::remove(self, key)
+    }
+
+    fn clear(&mut self) {
+        for chain in self.buckets.iter_mut() {
+            chain.clear();
+        }
+        self.slots_used = 0;
+    }
+
+    fn size(&self) -> usize {
+        self.slots_used
+    }
+
+    // Number of buckets; each can hold any number of entries.
+    fn capacity(&self) -> usize {
+        self.buckets.len()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        0
+    }
+}
+
+pub struct ChainedIter<'a, K: HashableKey, V: HashValue> {
+    buckets: std::slice::Iter<'a, Vec<KVPair<K, V>>>,
+    chain: std::slice::Iter<'a, KVPair<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for ChainedIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(kv) = self.chain.next() {
+                self.remaining -= 1;
+                return Some((&kv.key, &kv.value));
+            }
+            self.chain = self.buckets.next()?.iter();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for ChainedIter<'_, K, V> {}
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug, H: Hasher<K>> fmt::Debug
+    for ChainedHashTable<K, V, H>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> Default for ChainedHashTable<K, V, H> {
+    fn default() -> Self {
+        create_chained_hash_table(DEFAULT_CAPACITY)
+    }
+}
+
+pub fn create_chained_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>>(
+    buckets: usize,
+) -> ChainedHashTable<K, V, H> {
+    let mut table = Vec::with_capacity(buckets);
+    table.resize_with(buckets, Vec::new);
+    ChainedHashTable {
+        buckets: table,
+        slots_used: 0,
+        resize_count: 0,
+        ph_1: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdentityHasher, SimpleHasher};
+
+    struct NoDefaultValue(String);
+
+    #[test]
+    fn it_works() {
+        let mut ht: ChainedHashTable<i64, i64, SimpleHasher> = create_chained_hash_table(0);
+        for i in 0..1000 {
+            assert_eq!(ht.insert(i, i), None);
+        }
+        assert_eq!(ht.insert(9, 90), Some(9));
+        assert_eq!(ht.size(), 1000);
+        assert!(ht.size() as f64 <= ht.capacity() as f64 * MAX_AVERAGE_CHAIN_LENGTH);
+        for i in 0..1000 {
+            assert_eq!(ht.remove(&i), Some(if i == 9 { 90 } else { i }));
+        }
+        assert!(ht.is_empty());
+        assert_eq!(ht.iter().count(), 0);
+    }
+
+    #[test]
+    fn non_default_non_clone_values_test() {
+        let mut ht: ChainedHashTable<i64, NoDefaultValue, IdentityHasher> =
+            create_chained_hash_table(2);
+        for i in 0..50 {
+            ht.insert(i, NoDefaultValue(i.to_string()));
+        }
+        assert_eq!(ht.get(&42).map(|v| v.0.as_str()), Some("42"));
+        assert_eq!(ht.remove(&42).map(|v| v.0), Some("42".to_string()));
+    }
+
+    #[test]
+    fn delete_leaves_unrelated_keys_test() {
+        // every key lands in the same chain
+        let mut ht: ChainedHashTable<i64, i64, IdentityHasher> = create_chained_hash_table(1000);
+        let keys: Vec<i64> = (0..20).map(|i| i * 1000).collect();
+        for &key in keys.iter() {
+            ht.insert(key, key);
+        }
+        assert_eq!(ht.longest_chain(), keys.len());
+        for (i, &deleted) in keys.iter().enumerate() {
+            assert_eq!(ht.remove(&deleted), Some(deleted));
+            for &key in keys[i + 1..].iter() {
+                assert_eq!(ht.get(&key), Some(&key));
+            }
+            assert!(!ht.has(&deleted));
+            assert_eq!(ht.wasted_capacity(), 0);
+        }
+        assert!(ht.is_empty());
+    }
+}
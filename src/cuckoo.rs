@@ -0,0 +1,472 @@
+use crate::{
+    HashTable, HashValue, HashableKey, Hasher, InvariantViolation, KVPair, DEFAULT_CAPACITY,
+};
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+
+const MAX_KICKS: usize = 32;
+const STASH_SIZE: usize = 4;
+// Doublings a single insert may trigger before the hash functions are deemed
+// unable to separate the keys at all.
+const MAX_REHASHES: usize = 8;
+
+// Cuckoo hashing: every key lives at one of two slots, picked by H1 and H2, or
+// in a small stash, so a lookup checks at most two slots plus the stash.
+// Inserting into a full pair of slots evicts one occupant to its other slot,
+// and so on; when that chain runs too long the entry goes to the stash, and
+// once the stash is full the table grows.
+pub struct CuckooHashTable<K: HashableKey, V: HashValue, H1: Hasher<K>, H2: Hasher<K>> {
+    data: Vec<Option<KVPair<K, V>>>,
+    stash: Vec<KVPair<K, V>>,
+    slots_used: usize,
+    resize_count: usize,
+    ph_1: PhantomData<(H1, H2)>,
+}
+
+enum Location {
+    Slot(usize),
+    Stash(usize),
+}
+
+impl<K: HashableKey, V: HashValue, H1: Hasher<K>, H2: Hasher<K>> CuckooHashTable<K, V, H1, H2> {
+    fn positions<Q>(&self, key: &Q) -> (usize, usize)
+    where
+        Q: ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        let capacity = self.data.len();
+        (H1::hash(key) % capacity, H2::hash(key) % capacity)
+    }
+
+    fn locate<Q>(&self, key: &Q) -> Option<Location>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        if !self.data.is_empty() {
+            let (first, second) = self.positions(key);
+            for pos in [first, second] {
+                if matches!(&self.data[pos], Some(kv) if kv.key.borrow() == key) {
+                    return Some(Location::Slot(pos));
+                }
+            }
+        }
+        self.stash
+            .iter()
+            .position(|kv| kv.key.borrow() == key)
+            .map(Location::Stash)
+    }
+
+    // Runs the eviction chain for `kv`, handing back whichever entry is left
+    // without a slot when the chain gets too long.
+    fn try_place(&mut self, kv: KVPair<K, V>) -> Result<(), KVPair<K, V>> {
+        let (first, second) = self.positions(&kv.key);
+        for pos in [first, second] {
+            if self.data[pos].is_none() {
+                self.data[pos] = Some(kv);
+                return Ok(());
+            }
+        }
+        let mut carried = kv;
+        let mut pos = first;
+        for _ in 0..MAX_KICKS {
+            carried = self.data[pos].replace(carried).unwrap();
+            let (first, second) = self.positions(&carried.key);
+            pos = if pos == first { second } else { first };
+            if self.data[pos].is_none() {
+                self.data[pos] = Some(carried);
+                return Ok(());
+            }
+        }
+        Err(carried)
+    }
+
+    // Places `kv` in a slot or the stash, without growing.
+    fn try_store(&mut self, kv: KVPair<K, V>) -> Result<(), KVPair<K, V>> {
+        match self.try_place(kv) {
+            Ok(()) => Ok(()),
+            Err(homeless) if self.stash.len() < STASH_SIZE => {
+                self.stash.push(homeless);
+                Ok(())
+            }
+            Err(homeless) => Err(homeless),
+        }
+    }
+
+    // Rebuilds the table at `next_capacity` or larger, doubling until every
+    // entry (and `extra`) finds a place.
+    fn rehash(&mut self, mut next_capacity: usize, extra: Option<KVPair<K, V>>) {
+        let mut entries: Vec<KVPair<K, V>> = self.data.drain(..).flatten().collect();
+        entries.append(&mut self.stash);
+        entries.extend(extra);
+        for _ in 0..MAX_REHASHES {
+            self.data.resize_with(next_capacity, || None);
+            self.resize_count += 1;
+            let mut leftover = Vec::new();
+            for kv in entries.drain(..) {
+                if let Err(homeless) = self.try_store(kv) {
+                    leftover.push(homeless);
+                }
+            }
+            if leftover.is_empty() {
+                return;
+            }
+            entries = self.data.drain(..).flatten().collect();
+            entries.append(&mut self.stash);
+            entries.append(&mut leftover);
+            next_capacity = next_capacity.checked_mul(2).unwrap_or_else(|| {
+                panic!("couldn't resize from {}: capacity overflow", next_capacity)
+            });
+        }
+        panic!(
+            "couldn't place {} entries after {} rehashes: the hash functions don't separate the keys",
+            entries.len(),
+            MAX_REHASHES
+        );
+    }
+
+    fn grown_capacity(&self) -> usize {
+        std::cmp::max(self.data.len(), 1)
+            .checked_mul(2)
+            .unwrap_or_else(|| {
+                panic!(
+                    "couldn't resize from {}: capacity overflow",
+                    self.data.len()
+                )
+            })
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.locate(&key) {
+            Some(Location::Slot(pos)) => {
+                let kv = self.data[pos].as_mut().unwrap();
+                return Some(std::mem::replace(&mut kv.value, value));
+            }
+            Some(Location::Stash(index)) => {
+                return Some(std::mem::replace(&mut self.stash[index].value, value));
+            }
+            None => {}
+        }
+        self.slots_used += 1;
+        let kv = KVPair { key, value };
+        // two choices per key stop working well past half full
+        if self.slots_used > self.data.len() / 2 {
+            let next_capacity = self.grown_capacity();
+            self.rehash(next_capacity, Some(kv));
+        } else if let Err(homeless) = self.try_store(kv) {
+            let next_capacity = self.grown_capacity();
+            self.rehash(next_capacity, Some(homeless));
+        }
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        match self.locate(key)? {
+            Location::Slot(pos) => self.data[pos].as_ref().map(|kv| &kv.value),
+            Location::Stash(index) => Some(&self.stash[index].value),
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        match self.locate(key)? {
+            Location::Slot(pos) => self.data[pos].as_mut().map(|kv| &mut kv.value),
+            Location::Stash(index) => Some(&mut self.stash[index].value),
+        }
+    }
+
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        self.locate(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        let kv = match self.locate(key)? {
+            Location::Slot(pos) => self.data[pos].take().unwrap(),
+            Location::Stash(index) => self.stash.swap_remove(index),
+        };
+        self.slots_used -= 1;
+        // the freed slot may let a stashed entry back into the table
+        let stashed = std::mem::take(&mut self.stash);
+        for kv in stashed {
+            let (first, second) = self.positions(&kv.key);
+            match [first, second]
+                .into_iter()
+                .find(|&pos| self.data[pos].is_none())
+            {
+                Some(pos) => self.data[pos] = Some(kv),
+                None => self.stash.push(kv),
+            }
+        }
+        Some(kv.value)
+    }
+
+    pub fn iter(&self) -> CuckooIter<'_, K, V> {
+        CuckooIter {
+            slots: self.data.iter(),
+            stash: self.stash.iter(),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn resize_count(&self) -> usize {
+        self.resize_count
+    }
+
+    pub fn stash_len(&self) -> usize {
+        self.stash.len()
+    }
+
+    // Every entry sits at one of its two slots or in the stash.
+    pub fn debug_validate(&self) -> Result<(), InvariantViolation> {
+        let mut occupied = 0;
+        for (index, slot) in self.data.iter().enumerate() {
+            if let Some(kv) = slot {
+                occupied += 1;
+                let (first, second) = self.positions(&kv.key);
+                if index != first && index != second {
+                    return Err(InvariantViolation::UnreachableKey { index });
+                }
+            }
+        }
+        if self.stash.len() > STASH_SIZE {
+            return Err(InvariantViolation::StashOverflow {
+                len: self.stash.len(),
+            });
+        }
+        if occupied + self.stash.len() != self.slots_used {
+            return Err(InvariantViolation::SlotsUsedMismatch {
+                recorded: self.slots_used,
+                actual: occupied + self.stash.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H1: Hasher<K>, H2: Hasher<K>> HashTable<K, V>
+    for CuckooHashTable<K, V, H1, H2>
+{
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        CuckooHashTable::insert(self, key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        CuckooHashTable::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        CuckooHashTable::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        CuckooHashTable::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        CuckooHashTable::remove(self, key)
+    }
+
+    fn clear(&mut self) {
+        for element in self.data.iter_mut() {
+            element.take();
+        }
+        self.stash.clear();
+        self.slots_used = 0;
+    }
+
+    fn size(&self) -> usize {
+        self.slots_used
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        0
+    }
+}
+
+pub struct CuckooIter<'a, K: HashableKey, V: HashValue> {
+    slots: std::slice::Iter<'a, Option<KVPair<K, V>>>,
+    stash: std::slice::Iter<'a, KVPair<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for CuckooIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kv = match self.slots.by_ref().flatten().next() {
+            Some(kv) => kv,
+            None => self.stash.next()?,
+        };
+        self.remaining -= 1;
+        Some((&kv.key, &kv.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for CuckooIter<'_, K, V> {}
+
+impl<K, V, H1, H2> fmt::Debug for CuckooHashTable<K, V, H1, H2>
+where
+    K: HashableKey + fmt::Debug,
+    V: HashValue + fmt::Debug,
+    H1: Hasher<K>,
+    H2: Hasher<K>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H1: Hasher<K>, H2: Hasher<K>> Default
+    for CuckooHashTable<K, V, H1, H2>
+{
+    fn default() -> Self {
+        create_cuckoo_hash_table(DEFAULT_CAPACITY)
+    }
+}
+
+pub fn create_cuckoo_hash_table<K: HashableKey, V: HashValue, H1: Hasher<K>, H2: Hasher<K>>(
+    capacity: usize,
+) -> CuckooHashTable<K, V, H1, H2> {
+    let mut data = Vec::with_capacity(capacity);
+    data.resize_with(capacity, || None);
+    CuckooHashTable {
+        data,
+        stash: Vec::new(),
+        slots_used: 0,
+        resize_count: 0,
+        ph_1: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdentityHasher, SimpleHasher};
+
+    struct HighBitsHasher;
+    impl Hasher<i64> for HighBitsHasher {
+        fn hash(key: &i64) -> usize {
+            (*key >> 8) as usize
+        }
+    }
+
+    struct ZeroHasher;
+    impl Hasher<i64> for ZeroHasher {
+        fn hash(_key: &i64) -> usize {
+            0
+        }
+    }
+
+    type MixedCuckoo = CuckooHashTable<i64, i64, SimpleHasher, IdentityHasher>;
+
+    #[test]
+    fn it_works() {
+        let mut ht: MixedCuckoo = create_cuckoo_hash_table(8);
+        for i in 0..1000 {
+            assert_eq!(ht.insert(i, i), None);
+        }
+        assert_eq!(ht.insert(7, 70), Some(7));
+        assert_eq!(ht.size(), 1000);
+        assert_eq!(ht.debug_validate(), Ok(()));
+        for i in 0..1000 {
+            assert_eq!(ht.remove(&i), Some(if i == 7 { 70 } else { i }));
+        }
+        assert!(ht.is_empty());
+        let mut ht: MixedCuckoo = create_cuckoo_hash_table(0);
+        ht.insert(1, 1);
+        assert_eq!(ht.get(&1), Some(&1));
+    }
+
+    #[test]
+    fn grow_on_cycle_test() {
+        // below 256 both hashers send every multiple of 16 to slot 0 of a
+        // 16-slot table, so the eviction chain cycles long before it's half full
+        let mut ht: CuckooHashTable<i64, i64, IdentityHasher, HighBitsHasher> =
+            create_cuckoo_hash_table(16);
+        for i in 0..1 + STASH_SIZE as i64 {
+            ht.insert(i * 16, i);
+        }
+        assert_eq!(ht.stash_len(), STASH_SIZE);
+        assert_eq!(ht.resize_count(), 0);
+        ht.insert(80, 5);
+        assert_eq!(ht.resize_count(), 1);
+        assert_eq!(ht.capacity(), 32);
+        assert_eq!(ht.debug_validate(), Ok(()));
+        for i in 0..6 {
+            assert_eq!(ht.get(&(i * 16)), Some(&i));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "the hash functions don't separate the keys")]
+    fn displacement_loop_terminates_test() {
+        let mut ht: CuckooHashTable<i64, i64, ZeroHasher, ZeroHasher> =
+            create_cuckoo_hash_table(16);
+        for i in 0..100 {
+            ht.insert(i, i);
+        }
+    }
+
+    #[test]
+    fn stash_drains_on_remove_test() {
+        let mut ht: CuckooHashTable<i64, i64, IdentityHasher, HighBitsHasher> =
+            create_cuckoo_hash_table(16);
+        for i in 0..3 {
+            ht.insert(i * 16, i);
+        }
+        assert_eq!(ht.stash_len(), 2);
+        let in_slot = ht.data[0].as_ref().unwrap().key;
+        ht.remove(&in_slot);
+        assert_eq!(ht.stash_len(), 1);
+        assert_eq!(ht.debug_validate(), Ok(()));
+        assert_eq!(ht.size(), 2);
+        assert_eq!(ht.iter().count(), 2);
+    }
+
+    #[test]
+    fn debug_validate_catches_misplaced_key_test() {
+        let mut ht: CuckooHashTable<i64, i64, IdentityHasher, IdentityHasher> =
+            create_cuckoo_hash_table(8);
+        ht.insert(1, 1);
+        ht.data.swap(1, 3);
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::UnreachableKey { index: 3 })
+        );
+    }
+}
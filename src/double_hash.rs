@@ -0,0 +1,419 @@
+use crate::{
+    rehash_capacity, HashTable, HashValue, HashableKey, Hasher, InvariantViolation, KVPair,
+    DEFAULT_CAPACITY, DEFAULT_MAX_LOAD_FACTOR,
+};
+use bit_set::BitSet;
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+
+// Open addressing where H1 picks the home slot and H2 the distance between
+// probes. Capacities are powers of two and the step is forced odd, so it is
+// never 0 and always coprime with the capacity: every key's probe sequence
+// visits every slot. Removal leaves tombstones, which a rehash clears out.
+pub struct DoubleHashTable<K: HashableKey, V: HashValue, H1: Hasher<K>, H2: Hasher<K>> {
+    data: Vec<Option<KVPair<K, V>>>,
+    deleted: BitSet,
+    slots_used: usize,
+    deleted_slots: usize,
+    resize_count: usize,
+    ph_1: PhantomData<(H1, H2)>,
+}
+
+enum Probe {
+    Found(usize, usize),
+    Vacant(Option<usize>),
+}
+
+fn probe_step(hash: usize) -> usize {
+    hash | 1
+}
+
+impl<K: HashableKey, V: HashValue, H1: Hasher<K>, H2: Hasher<K>> DoubleHashTable<K, V, H1, H2> {
+    // Returns the slot holding a match and how many probes it took, or the
+    // first tombstone or empty slot the key could go in.
+    fn probe<F: Fn(&K) -> bool>(&self, home: usize, step: usize, is_match: F) -> Probe {
+        let capacity = self.data.len();
+        if capacity == 0 {
+            return Probe::Vacant(None);
+        }
+        let mask = capacity - 1;
+        let step = probe_step(step) & mask;
+        let mut reusable = None;
+        let mut pos = home & mask;
+        for probes in 0..capacity {
+            match &self.data[pos] {
+                Some(kv) if is_match(&kv.key) => return Probe::Found(pos, probes),
+                Some(_) => {}
+                None if self.deleted.contains(pos) => {
+                    reusable.get_or_insert(pos);
+                }
+                None => return Probe::Vacant(reusable.or(Some(pos))),
+            }
+            pos = (pos + step) & mask;
+        }
+        Probe::Vacant(reusable)
+    }
+
+    fn get_pos<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        match self.probe(H1::hash(key), H2::hash(key), |k| k.borrow() == key) {
+            Probe::Found(pos, _) => Some(pos),
+            Probe::Vacant(_) => None,
+        }
+    }
+
+    fn free_pos(&self, key: &K) -> usize {
+        match self.probe(H1::hash(key), H2::hash(key), |_| false) {
+            Probe::Vacant(Some(pos)) => pos,
+            _ => panic!("no free slot available after growing"),
+        }
+    }
+
+    fn max_slots(capacity: usize) -> usize {
+        (capacity as f64 * DEFAULT_MAX_LOAD_FACTOR) as usize
+    }
+
+    fn resize(&mut self, next_capacity: usize) {
+        let mut new_data = Vec::with_capacity(next_capacity);
+        new_data.resize_with(next_capacity, || None);
+        let old_data = std::mem::replace(&mut self.data, new_data);
+        self.deleted = BitSet::new();
+        self.deleted_slots = 0;
+        for kv in old_data.into_iter().flatten() {
+            let pos = self.free_pos(&kv.key);
+            self.data[pos] = Some(kv);
+        }
+        self.resize_count += 1;
+    }
+
+    // Returns whether the table was rehashed, which moves every entry.
+    fn grow_if_needed(&mut self) -> bool {
+        let capacity = self.data.len();
+        let max_slots = Self::max_slots(capacity);
+        match rehash_capacity(capacity, max_slots, self.slots_used, self.deleted_slots) {
+            Some(next_capacity) => {
+                self.resize(next_capacity);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let vacant = match self.probe(H1::hash(&key), H2::hash(&key), |k| *k == key) {
+            Probe::Found(pos, _) => {
+                let kv = self.data[pos].as_mut().unwrap();
+                return Some(std::mem::replace(&mut kv.value, value));
+            }
+            Probe::Vacant(vacant) => vacant,
+        };
+        let pos = match vacant {
+            Some(pos) if !self.grow_if_needed() => pos,
+            _ => self.free_pos(&key),
+        };
+        if self.deleted.remove(pos) {
+            self.deleted_slots -= 1;
+        }
+        self.data[pos] = Some(KVPair { key, value });
+        self.slots_used += 1;
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.data[pos].as_ref().map(|kv| &kv.value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.data[pos].as_mut().map(|kv| &mut kv.value)
+    }
+
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        self.get_pos(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H1: Hasher<Q>,
+        H2: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.deleted.insert(pos);
+        self.deleted_slots += 1;
+        self.slots_used -= 1;
+        self.data[pos].take().map(|kv| kv.value)
+    }
+
+    pub fn iter(&self) -> DoubleHashIter<'_, K, V> {
+        DoubleHashIter {
+            slots: self.data.iter(),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn resize_count(&self) -> usize {
+        self.resize_count
+    }
+
+    // Mean number of extra probes a successful lookup of each entry takes.
+    pub fn average_probe_length(&self) -> f64 {
+        if self.slots_used == 0 {
+            return 0.0;
+        }
+        let total: usize = self
+            .data
+            .iter()
+            .flatten()
+            .map(|kv| {
+                match self.probe(H1::hash(&kv.key), H2::hash(&kv.key), |k| {
+                    std::ptr::eq(k, &kv.key)
+                }) {
+                    Probe::Found(_, probes) => probes,
+                    Probe::Vacant(_) => unreachable!("every entry is reachable"),
+                }
+            })
+            .sum();
+        total as f64 / self.slots_used as f64
+    }
+
+    pub fn debug_validate(&self) -> Result<(), InvariantViolation> {
+        let mut occupied = 0;
+        for (index, slot) in self.data.iter().enumerate() {
+            if let Some(kv) = slot {
+                occupied += 1;
+                let found = self.probe(H1::hash(&kv.key), H2::hash(&kv.key), |k| {
+                    std::ptr::eq(k, &kv.key)
+                });
+                if !matches!(found, Probe::Found(pos, _) if pos == index) {
+                    return Err(InvariantViolation::UnreachableKey { index });
+                }
+            }
+        }
+        if occupied != self.slots_used {
+            return Err(InvariantViolation::SlotsUsedMismatch {
+                recorded: self.slots_used,
+                actual: occupied,
+            });
+        }
+        if self.deleted.len() != self.deleted_slots {
+            return Err(InvariantViolation::DeletedSlotsMismatch {
+                recorded: self.deleted_slots,
+                actual: self.deleted.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H1: Hasher<K>, H2: Hasher<K>> HashTable<K, V>
+    for DoubleHashTable<K, V, H1, H2>
+{
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        DoubleHashTable::insert(self, key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        DoubleHashTable::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        DoubleHashTable::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        DoubleHashTable::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        DoubleHashTable::remove(self, key)
+    }
+
+    fn clear(&mut self) {
+        for element in self.data.iter_mut() {
+            element.take();
+        }
+        self.deleted.clear();
+        self.slots_used = 0;
+        self.deleted_slots = 0;
+    }
+
+    fn size(&self) -> usize {
+        self.slots_used
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        self.deleted_slots
+    }
+}
+
+pub struct DoubleHashIter<'a, K: HashableKey, V: HashValue> {
+    slots: std::slice::Iter<'a, Option<KVPair<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for DoubleHashIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kv = self.slots.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((&kv.key, &kv.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for DoubleHashIter<'_, K, V> {}
+
+impl<K, V, H1, H2> fmt::Debug for DoubleHashTable<K, V, H1, H2>
+where
+    K: HashableKey + fmt::Debug,
+    V: HashValue + fmt::Debug,
+    H1: Hasher<K>,
+    H2: Hasher<K>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H1: Hasher<K>, H2: Hasher<K>> Default
+    for DoubleHashTable<K, V, H1, H2>
+{
+    fn default() -> Self {
+        create_double_hash_table(DEFAULT_CAPACITY)
+    }
+}
+
+// The capacity is rounded up to a power of two.
+pub fn create_double_hash_table<K: HashableKey, V: HashValue, H1: Hasher<K>, H2: Hasher<K>>(
+    capacity: usize,
+) -> DoubleHashTable<K, V, H1, H2> {
+    let capacity = capacity.max(1).next_power_of_two();
+    let mut data = Vec::with_capacity(capacity);
+    data.resize_with(capacity, || None);
+    DoubleHashTable {
+        data,
+        deleted: BitSet::new(),
+        slots_used: 0,
+        deleted_slots: 0,
+        resize_count: 0,
+        ph_1: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        create_no_tombstones_hash_table, IdentityHasher, SimpleHashTableNoTombstones, SimpleHasher,
+    };
+    use std::collections::HashSet;
+
+    struct ZeroHasher;
+    impl Hasher<i64> for ZeroHasher {
+        fn hash(_key: &i64) -> usize {
+            0
+        }
+    }
+
+    #[test]
+    fn odd_step_visits_every_slot_test() {
+        for capacity in [1, 2, 16, 256] {
+            for hash in [0, 1, 2, 6, 128, usize::MAX - 1] {
+                let step = probe_step(hash) & (capacity - 1);
+                let mut visited = HashSet::new();
+                let mut pos = 0;
+                for _ in 0..capacity {
+                    visited.insert(pos);
+                    pos = (pos + step) & (capacity - 1);
+                }
+                assert_eq!(visited.len(), capacity, "hash {}", hash);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_step_hash_test() {
+        // a second hash of 0 still yields a step of 1
+        let mut ht: DoubleHashTable<i64, i64, ZeroHasher, ZeroHasher> = create_double_hash_table(8);
+        for i in 0..100 {
+            ht.insert(i, i);
+        }
+        for i in 0..100 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+        assert_eq!(ht.capacity(), 256);
+    }
+
+    #[test]
+    fn multiples_of_capacity_test() {
+        // every key has home slot 0, which degenerates linear probing into one
+        // long cluster; the second hash scatters their probe sequences
+        let capacity = 1024;
+        let keys: Vec<i64> = (0..600).map(|i| i * capacity as i64).collect();
+        let mut double: DoubleHashTable<i64, i64, IdentityHasher, SimpleHasher> =
+            create_double_hash_table(capacity);
+        let mut linear: SimpleHashTableNoTombstones<i64, i64, IdentityHasher> =
+            create_no_tombstones_hash_table(capacity);
+        for &key in keys.iter() {
+            double.insert(key, key);
+            linear.insert(key, key);
+        }
+        assert_eq!(double.capacity(), capacity);
+        let linear_probes: usize = keys.iter().filter_map(|k| linear.probe_distance(k)).sum();
+        let linear_average = linear_probes as f64 / keys.len() as f64;
+        assert!(linear_average > 250.0, "linear {}", linear_average);
+        assert!(
+            double.average_probe_length() < 3.0,
+            "double {}",
+            double.average_probe_length()
+        );
+    }
+
+    #[test]
+    fn debug_validate_catches_missing_tombstone_test() {
+        let mut ht: DoubleHashTable<i64, i64, IdentityHasher, ZeroHasher> =
+            create_double_hash_table(16);
+        ht.insert(0, 0);
+        ht.insert(16, 16);
+        ht.data[0] = None;
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::UnreachableKey { index: 1 })
+        );
+    }
+}
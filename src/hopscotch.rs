@@ -0,0 +1,602 @@
+use crate::{
+    grown_capacity, HashTable, HashValue, HashableKey, Hasher, InvariantViolation, KVPair,
+    DEFAULT_CAPACITY, DEFAULT_GROWTH_FACTOR,
+};
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+
+const HOP_RANGE: usize = 32;
+const HOPSCOTCH_MAX_LOAD_FACTOR: f64 = 0.9;
+// Growths a single insert may trigger before the leftover entries are put in
+// the overflow list instead.
+const MAX_REHASHES: usize = 8;
+
+// Hopscotch hashing: every entry lives within HOP_RANGE slots of its home, and
+// each bucket keeps a bitmap of which of those slots hold its entries. A lookup
+// reads one bitmap and at most HOP_RANGE slots. Insertion finds the nearest
+// free slot and, while it is too far away, hops it backwards by moving a closer
+// entry into it. Entries whose neighborhood is filled with keys of the same hash,
+// which no amount of growing would split, go to an overflow list instead.
+pub struct HopscotchHashTable<K: HashableKey, V: HashValue, H: Hasher<K>> {
+    data: Vec<Option<KVPair<K, V>>>,
+    hop: Vec<u32>,
+    overflow: Vec<KVPair<K, V>>,
+    slots_used: usize,
+    resize_count: usize,
+    ph_1: PhantomData<H>,
+}
+
+enum Location {
+    Slot(usize),
+    Overflow(usize),
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> HopscotchHashTable<K, V, H> {
+    // Tables smaller than HOP_RANGE wrap their neighborhoods onto themselves.
+    fn neighborhood(&self) -> usize {
+        std::cmp::min(HOP_RANGE, self.data.len())
+    }
+
+    fn distance(&self, from: usize, to: usize) -> usize {
+        let capacity = self.data.len();
+        (to + capacity - from) % capacity
+    }
+
+    fn neighbors(&self, bucket: usize) -> impl Iterator<Item = usize> + '_ {
+        let bits = self.hop[bucket];
+        let capacity = self.data.len();
+        (0..self.neighborhood())
+            .filter(move |offset| bits & (1 << offset) != 0)
+            .map(move |offset| (bucket + offset) % capacity)
+    }
+
+    fn locate<Q>(&self, key: &Q) -> Option<Location>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        if !self.data.is_empty() {
+            let home = H::hash(key) % self.data.len();
+            let pos = self.neighbors(home).find(|&pos| {
+                #[cfg(test)]
+                tests::count_slot_read();
+                matches!(&self.data[pos], Some(kv) if kv.key.borrow() == key)
+            });
+            if let Some(pos) = pos {
+                return Some(Location::Slot(pos));
+            }
+            // entries only overflow out of a full neighborhood, so a miss
+            // anywhere else is final
+            if self.hop[home].count_ones() < self.neighborhood() as u32 {
+                return None;
+            }
+        }
+        self.overflow
+            .iter()
+            .position(|kv| {
+                #[cfg(test)]
+                tests::count_slot_read();
+                kv.key.borrow() == key
+            })
+            .map(Location::Overflow)
+    }
+
+    // Growing splits a neighborhood only if it has room or holds keys whose
+    // hash differs from `hash`; otherwise the keys land together at any size.
+    fn growth_helps(&self, hash: usize) -> bool {
+        let home = hash % self.data.len();
+        self.hop[home].count_ones() < self.neighborhood() as u32
+            || self
+                .neighbors(home)
+                .any(|pos| matches!(&self.data[pos], Some(kv) if H::hash(&kv.key) != hash))
+    }
+
+    // Hands `kv` back if no free slot can be hopped into its neighborhood.
+    fn try_place(&mut self, kv: KVPair<K, V>) -> Result<(), KVPair<K, V>> {
+        let capacity = self.data.len();
+        let neighborhood = self.neighborhood();
+        let home = H::hash(&kv.key) % capacity;
+        let mut free = match (0..capacity)
+            .map(|step| (home + step) % capacity)
+            .find(|&pos| self.data[pos].is_none())
+        {
+            Some(pos) => pos,
+            None => return Err(kv),
+        };
+        while self.distance(home, free) >= neighborhood {
+            // the earliest entry, of the furthest bucket, that may move into `free`
+            let hop = (1..neighborhood).rev().find_map(|back| {
+                let bucket = (free + capacity - back) % capacity;
+                let bits = self.hop[bucket];
+                (0..back)
+                    .find(|offset| bits & (1 << offset) != 0)
+                    .map(|offset| (bucket, offset, back))
+            });
+            let (bucket, offset, back) = match hop {
+                Some(hop) => hop,
+                None => return Err(kv),
+            };
+            let from = (bucket + offset) % capacity;
+            self.data[free] = self.data[from].take();
+            self.hop[bucket] &= !(1 << offset);
+            self.hop[bucket] |= 1 << back;
+            free = from;
+        }
+        self.data[free] = Some(kv);
+        self.hop[home] |= 1 << self.distance(home, free);
+        Ok(())
+    }
+
+    fn resize(&mut self, mut next_capacity: usize, extra: Option<KVPair<K, V>>) {
+        let mut entries: Vec<KVPair<K, V>> = self.data.drain(..).flatten().collect();
+        entries.append(&mut self.overflow);
+        entries.extend(extra);
+        for _ in 0..MAX_REHASHES {
+            self.data.resize_with(next_capacity, || None);
+            self.hop = vec![0; next_capacity];
+            self.resize_count += 1;
+            let mut leftover = Vec::new();
+            for kv in entries.drain(..) {
+                if let Err(kv) = self.try_place(kv) {
+                    leftover.push(kv);
+                }
+            }
+            let (mut splittable, mut stuck): (Vec<_>, Vec<_>) = leftover
+                .into_iter()
+                .partition(|kv| self.growth_helps(H::hash(&kv.key)));
+            self.overflow.append(&mut stuck);
+            if splittable.is_empty() {
+                return;
+            }
+            entries = self.data.drain(..).flatten().collect();
+            entries.append(&mut self.overflow);
+            entries.append(&mut splittable);
+            next_capacity =
+                grown_capacity(next_capacity, DEFAULT_GROWTH_FACTOR).unwrap_or_else(|| {
+                    panic!("couldn't resize from {}: capacity overflow", next_capacity)
+                });
+        }
+        self.overflow.append(&mut entries);
+    }
+
+    fn next_capacity(&self) -> usize {
+        grown_capacity(self.data.len(), DEFAULT_GROWTH_FACTOR).unwrap_or_else(|| {
+            panic!(
+                "couldn't resize from {}: capacity overflow",
+                self.data.len()
+            )
+        })
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(location) = self.locate(&key) {
+            let kv = match location {
+                Location::Slot(pos) => self.data[pos].as_mut().unwrap(),
+                Location::Overflow(index) => &mut self.overflow[index],
+            };
+            return Some(std::mem::replace(&mut kv.value, value));
+        }
+        self.slots_used += 1;
+        let kv = KVPair { key, value };
+        let in_slots = self.slots_used - self.overflow.len();
+        if in_slots as f64 > self.data.len() as f64 * HOPSCOTCH_MAX_LOAD_FACTOR {
+            let next_capacity = self.next_capacity();
+            self.resize(next_capacity, Some(kv));
+        } else if let Err(kv) = self.try_place(kv) {
+            if self.growth_helps(H::hash(&kv.key)) {
+                let next_capacity = self.next_capacity();
+                self.resize(next_capacity, Some(kv));
+            } else {
+                self.overflow.push(kv);
+            }
+        }
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        match self.locate(key)? {
+            Location::Slot(pos) => self.data[pos].as_ref().map(|kv| &kv.value),
+            Location::Overflow(index) => Some(&self.overflow[index].value),
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        match self.locate(key)? {
+            Location::Slot(pos) => self.data[pos].as_mut().map(|kv| &mut kv.value),
+            Location::Overflow(index) => Some(&mut self.overflow[index].value),
+        }
+    }
+
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.locate(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = match self.locate(key)? {
+            Location::Slot(pos) => pos,
+            Location::Overflow(index) => {
+                self.slots_used -= 1;
+                return Some(self.overflow.swap_remove(index).value);
+            }
+        };
+        let home = H::hash(key) % self.data.len();
+        self.hop[home] &= !(1 << self.distance(home, pos));
+        self.slots_used -= 1;
+        let kv = self.data[pos].take().unwrap();
+        // the freed slot is in `home`'s neighborhood, so an overflowed entry
+        // with the same home can move into it
+        let capacity = self.data.len();
+        if let Some(index) = self
+            .overflow
+            .iter()
+            .position(|kv| H::hash(&kv.key) % capacity == home)
+        {
+            let overflowed = self.overflow.swap_remove(index);
+            self.data[pos] = Some(overflowed);
+            self.hop[home] |= 1 << self.distance(home, pos);
+        }
+        Some(kv.value)
+    }
+
+    pub fn iter(&self) -> HopscotchIter<'_, K, V> {
+        HopscotchIter {
+            slots: self.data.iter(),
+            overflow: self.overflow.iter(),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn resize_count(&self) -> usize {
+        self.resize_count
+    }
+
+    pub fn overflow_len(&self) -> usize {
+        self.overflow.len()
+    }
+
+    pub fn debug_validate(&self) -> Result<(), InvariantViolation> {
+        let capacity = self.data.len();
+        for bucket in 0..capacity {
+            for offset in 0..HOP_RANGE {
+                if self.hop[bucket] & (1 << offset) == 0 {
+                    continue;
+                }
+                let pos = (bucket + offset) % capacity;
+                let belongs = offset < self.neighborhood()
+                    && matches!(&self.data[pos], Some(kv) if H::hash(&kv.key) % capacity == bucket);
+                if !belongs {
+                    return Err(InvariantViolation::StaleNeighborBit { bucket, offset });
+                }
+            }
+        }
+        let mut occupied = 0;
+        for (index, slot) in self.data.iter().enumerate() {
+            if let Some(kv) = slot {
+                occupied += 1;
+                let home = H::hash(&kv.key) % capacity;
+                if !self.neighbors(home).any(|pos| pos == index) {
+                    return Err(InvariantViolation::UnreachableKey { index });
+                }
+            }
+        }
+        for (i, kv) in self.overflow.iter().enumerate() {
+            if capacity > 0
+                && self.hop[H::hash(&kv.key) % capacity].count_ones() < self.neighborhood() as u32
+            {
+                // overflowed entries are indexed past the end of `data`
+                return Err(InvariantViolation::UnreachableKey {
+                    index: capacity + i,
+                });
+            }
+        }
+        if occupied + self.overflow.len() != self.slots_used {
+            return Err(InvariantViolation::SlotsUsedMismatch {
+                recorded: self.slots_used,
+                actual: occupied + self.overflow.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> HashTable<K, V> for HopscotchHashTable<K, V, H> {
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        HopscotchHashTable::insert(self, key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        HopscotchHashTable::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        HopscotchHashTable::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        HopscotchHashTable::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        HopscotchHashTable::remove(self, key)
+    }
+
+    fn clear(&mut self) {
+        for element in self.data.iter_mut() {
+            element.take();
+        }
+        self.hop.iter_mut().for_each(|bits| *bits = 0);
+        self.overflow.clear();
+        self.slots_used = 0;
+    }
+
+    fn size(&self) -> usize {
+        self.slots_used
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        0
+    }
+}
+
+pub struct HopscotchIter<'a, K: HashableKey, V: HashValue> {
+    slots: std::slice::Iter<'a, Option<KVPair<K, V>>>,
+    overflow: std::slice::Iter<'a, KVPair<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for HopscotchIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kv = match self.slots.by_ref().flatten().next() {
+            Some(kv) => kv,
+            None => self.overflow.next()?,
+        };
+        self.remaining -= 1;
+        Some((&kv.key, &kv.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for HopscotchIter<'_, K, V> {}
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug, H: Hasher<K>> fmt::Debug
+    for HopscotchHashTable<K, V, H>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> Default for HopscotchHashTable<K, V, H> {
+    fn default() -> Self {
+        create_hopscotch_hash_table(DEFAULT_CAPACITY)
+    }
+}
+
+pub fn create_hopscotch_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>>(
+    capacity: usize,
+) -> HopscotchHashTable<K, V, H> {
+    let mut data = Vec::with_capacity(capacity);
+    data.resize_with(capacity, || None);
+    HopscotchHashTable {
+        data,
+        hop: vec![0; capacity],
+        overflow: Vec::new(),
+        slots_used: 0,
+        resize_count: 0,
+        ph_1: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdentityHasher, SimpleHasher};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::cell::Cell;
+
+    struct ZeroHasher;
+    impl Hasher<i64> for ZeroHasher {
+        fn hash(_key: &i64) -> usize {
+            0
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        let mut ht: HopscotchHashTable<i64, i64, SimpleHasher> = create_hopscotch_hash_table(0);
+        for i in 0..1000 {
+            assert_eq!(ht.insert(i, i), None);
+        }
+        assert_eq!(ht.insert(3, 30), Some(3));
+        assert_eq!(ht.size(), 1000);
+        assert_eq!(ht.debug_validate(), Ok(()));
+        for i in 0..1000 {
+            assert_eq!(ht.remove(&i), Some(if i == 3 { 30 } else { i }));
+        }
+        assert!(ht.is_empty());
+        assert_eq!(ht.debug_validate(), Ok(()));
+    }
+
+    #[test]
+    fn hops_free_slot_into_neighborhood_test() {
+        let capacity = 128;
+        let mut ht: HopscotchHashTable<i64, i64, IdentityHasher> =
+            create_hopscotch_hash_table(capacity);
+        // slots 0..40 each hold their own key, so the free slot for a second
+        // key with home 0 starts 40 away and entries have to hop to make room
+        for key in 0..40 {
+            ht.insert(key, key);
+        }
+        ht.insert(capacity as i64, -1);
+        assert_eq!(ht.capacity(), capacity);
+        assert_eq!(ht.get(&(capacity as i64)), Some(&-1));
+        assert_eq!(ht.debug_validate(), Ok(()));
+        for key in 0..40 {
+            assert_eq!(ht.get(&key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn full_neighborhood_grows_test() {
+        let mut ht: HopscotchHashTable<i64, i64, IdentityHasher> = create_hopscotch_hash_table(256);
+        // 33 keys with home 0 can't share one 32-slot neighborhood
+        for i in 0..HOP_RANGE as i64 + 1 {
+            ht.insert(i * 256, i);
+        }
+        assert!(ht.capacity() > 256);
+        assert_eq!(ht.debug_validate(), Ok(()));
+        assert_eq!(ht.size(), HOP_RANGE + 1);
+    }
+
+    #[test]
+    fn constant_hasher_test() {
+        let mut ht: HopscotchHashTable<i64, i64, ZeroHasher> = create_hopscotch_hash_table(64);
+        let n = 4 * HOP_RANGE as i64 + 1;
+        for i in 0..n {
+            assert_eq!(HashTable::insert(&mut ht, i, i), None);
+        }
+        // growing can't split keys that all hash alike, so it isn't tried
+        assert_eq!(ht.capacity(), 64);
+        assert_eq!(ht.resize_count(), 0);
+        assert_eq!(ht.overflow_len(), n as usize - HOP_RANGE);
+        assert_eq!(ht.size(), n as usize);
+        assert_eq!(ht.debug_validate(), Ok(()));
+        assert_eq!(ht.insert(n - 1, -1), Some(n - 1));
+        for i in 0..n - 1 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+        assert_eq!(ht.iter().count(), n as usize);
+
+        // removing from the neighborhood pulls overflowed entries back in
+        for i in 0..HOP_RANGE as i64 {
+            assert_eq!(ht.remove(&i), Some(i));
+        }
+        assert_eq!(ht.overflow_len(), n as usize - 2 * HOP_RANGE);
+        assert_eq!(ht.debug_validate(), Ok(()));
+        assert_eq!(ht.get(&(n - 1)), Some(&-1));
+        for i in HOP_RANGE as i64..n - 1 {
+            assert_eq!(ht.remove(&i), Some(i));
+        }
+        assert_eq!(ht.remove(&(n - 1)), Some(-1));
+        assert!(ht.is_empty());
+        assert_eq!(ht.overflow_len(), 0);
+        assert_eq!(ht.debug_validate(), Ok(()));
+    }
+
+    thread_local! {
+        static SLOT_READS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    pub(super) fn count_slot_read() {
+        SLOT_READS.with(|count| count.set(count.get() + 1));
+    }
+
+    fn slot_reads() -> usize {
+        SLOT_READS.with(|count| count.replace(0))
+    }
+
+    // 64 consecutive keys share each hash.
+    struct CoarseHasher;
+    impl Hasher<i64> for CoarseHasher {
+        fn hash(key: &i64) -> usize {
+            (*key / 64) as usize
+        }
+    }
+
+    #[test]
+    fn high_load_lookup_bound_test() {
+        let capacity = 4096;
+        let mut ht: HopscotchHashTable<i64, i64, CoarseHasher> =
+            create_hopscotch_hash_table(capacity);
+        // fills bucket 0 and overflows 8 keys that no growth can separate
+        let overflowed = 8;
+        for key in 0..(HOP_RANGE + overflowed) as i64 {
+            ht.insert(key, key);
+        }
+        assert_eq!(ht.overflow_len(), overflowed);
+        let mut rng = StdRng::seed_from_u64(86);
+        let mut keys = Vec::new();
+        while ht.size() < capacity * 9 / 10 {
+            let key = rng.gen_range(64..1 << 40);
+            ht.insert(key, key);
+            keys.push(key);
+        }
+        assert_eq!(ht.overflow_len(), overflowed);
+        assert_eq!(ht.debug_validate(), Ok(()));
+
+        // outside bucket 0, lookups read only the bucket's own entries and
+        // misses never fall through to the overflow
+        let capacity = ht.capacity();
+        let bits = |key: i64| ht.hop[CoarseHasher::hash(&key) % capacity].count_ones() as usize;
+        slot_reads();
+        for &key in keys.iter() {
+            assert_eq!(ht.get(&key), Some(&key));
+            assert!(slot_reads() <= bits(key));
+        }
+        for _ in 0..10_000 {
+            let key = rng.gen_range(64..1 << 40);
+            if CoarseHasher::hash(&key) % capacity != 0 && ht.get(&key).is_none() {
+                assert_eq!(slot_reads(), bits(key));
+            }
+            slot_reads();
+        }
+        for key in 0..(HOP_RANGE + overflowed) as i64 {
+            assert_eq!(ht.get(&key), Some(&key));
+            assert!(slot_reads() <= HOP_RANGE + overflowed);
+        }
+        assert_eq!(ht.get(&-1), None);
+        assert_eq!(slot_reads(), HOP_RANGE + overflowed);
+    }
+
+    #[test]
+    fn debug_validate_catches_corruption_test() {
+        let mut ht: HopscotchHashTable<i64, i64, IdentityHasher> = create_hopscotch_hash_table(64);
+        ht.insert(1, 1);
+        ht.hop[5] |= 1 << 2;
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::StaleNeighborBit {
+                bucket: 5,
+                offset: 2
+            })
+        );
+        ht.hop[5] = 0;
+        ht.hop[1] = 0;
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::UnreachableKey { index: 1 })
+        );
+    }
+}
@@ -1,328 +1,6302 @@
 use bit_set::BitSet;
 use core::panic;
-use rand::prelude::*;
-use std::collections::HashSet;
+use rand::Rng;
+use std::borrow::Borrow;
+use std::fmt;
 use std::fmt::Display;
 use std::marker::PhantomData;
 use std::option::Option;
 
-pub trait HashableKey: std::cmp::PartialEq + Default + Display {}
-pub trait HashValue: Default + Clone + Display {}
-pub trait Hasher<K: HashableKey> {
+mod adapter;
+mod chained;
+mod cuckoo;
+mod double_hash;
+mod hopscotch;
+mod no_tombstones;
+mod prefetch;
+mod quadratic;
+mod robin_hood;
+mod set;
+mod small;
+mod soa;
+mod swiss;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub use adapter::StdHashMapAdapter;
+pub use chained::{create_chained_hash_table, ChainedHashTable, ChainedIter};
+pub use cuckoo::{create_cuckoo_hash_table, CuckooHashTable, CuckooIter};
+pub use double_hash::{create_double_hash_table, DoubleHashIter, DoubleHashTable};
+pub use hopscotch::{create_hopscotch_hash_table, HopscotchHashTable, HopscotchIter};
+pub use no_tombstones::{
+    create_no_tombstones_hash_table, NoTombstonesIter, SimpleHashTableNoTombstones,
+};
+pub use quadratic::{create_quadratic_hash_table, QuadraticHashTable, QuadraticIter};
+pub use robin_hood::{create_robin_hood_hash_table, RobinHoodHashTable, RobinHoodIter};
+pub use set::{create_simple_hash_set, SetIntoIter, SetIter, SimpleHashSet};
+pub use small::{create_small_hash_table, SmallHashTable, SmallIter};
+pub use soa::{create_soa_hash_table, SoaHashTable, SoaIter};
+pub use swiss::{create_swiss_hash_table, SwissHashTable, SwissIter};
+
+pub trait HashableKey: Eq {}
+pub trait HashValue {}
+impl<T> HashValue for T {}
+pub trait Hasher<K: ?Sized> {
     fn hash(key: &K) -> usize;
 }
 
 pub trait HashTable<K: HashableKey, V: HashValue> {
-    fn insert(&mut self, key: K, value: V);
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
     fn has(&self, key: &K) -> bool;
-    fn get<'a>(&self, key: &'a K) -> Option<V>;
-    fn delete(&mut self, key: &K);
+    fn get(&self, key: &K) -> Option<&V>;
+    fn get_mut(&mut self, key: &K) -> Option<&mut V>;
+    fn get_cloned(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.get(key).cloned()
+    }
+    fn remove(&mut self, key: &K) -> Option<V>;
+    fn delete(&mut self, key: &K) {
+        self.remove(key);
+    }
+    fn clear(&mut self);
     fn size(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+    /// Number of slots. Implementations may grow before every slot is in use,
+    /// so this can exceed the number of entries that fit without resizing.
     fn capacity(&self) -> usize;
     fn wasted_capacity(&self) -> usize;
 }
 
-#[derive(Default, Debug)]
+#[derive(Debug, Clone)]
 struct KVPair<K: HashableKey, V: HashValue> {
     key: K,
     value: V,
 }
 
-type S<K, V> = Option<KVPair<K, V>>;
-type VecS<K, V> = Vec<S<K, V>>;
+const DEFAULT_CAPACITY: usize = 16;
+const DEFAULT_MAX_LOAD_FACTOR: f64 = 0.75;
+const DEFAULT_GROWTH_FACTOR: f64 = 2.0;
+const DEFAULT_TOMBSTONE_THRESHOLD: f64 = 0.25;
+const RANDOM_ENTRY_RETRIES: usize = 32;
+const DEFAULT_MIGRATION_BATCH: usize = 32;
+// How many keys ahead get_batch prefetches home slots for.
+const BATCH_PREFETCH_DISTANCE: usize = 8;
+
+// SimpleHashTable's slots also keep each key's hash, so a resize never calls
+// the hasher and a probe only compares keys whose hash matches. The stamp is
+// unique per insert, so a SlotHandle can tell its entry from a later one
+// placed in the same slot.
+#[derive(Debug, Clone)]
+struct HashedKVPair<K: HashableKey, V: HashValue> {
+    hash: usize,
+    key: K,
+    value: V,
+    stamp: u64,
+}
+
+// A removed entry leaves a Tombstone so probes for keys placed after it keep
+// going; only Empty ends a probe.
+#[derive(Debug, Clone)]
+enum Slot<K: HashableKey, V: HashValue> {
+    Empty,
+    Tombstone,
+    Occupied(HashedKVPair<K, V>),
+}
+
+impl<K: HashableKey, V: HashValue> Slot<K, V> {
+    fn occupied(&self) -> Option<&HashedKVPair<K, V>> {
+        match self {
+            Slot::Occupied(kv) => Some(kv),
+            _ => None,
+        }
+    }
+
+    fn occupied_mut(&mut self) -> Option<&mut HashedKVPair<K, V>> {
+        match self {
+            Slot::Occupied(kv) => Some(kv),
+            _ => None,
+        }
+    }
+
+    fn into_occupied(self) -> Option<HashedKVPair<K, V>> {
+        match self {
+            Slot::Occupied(kv) => Some(kv),
+            _ => None,
+        }
+    }
+
+    fn state(&self) -> SlotState {
+        match self {
+            Slot::Empty => SlotState::Empty,
+            Slot::Tombstone => SlotState::Tombstone,
+            Slot::Occupied(_) => SlotState::Occupied,
+        }
+    }
+
+    // Empties the slot, returning the entry it held, if any.
+    fn take(&mut self) -> Option<HashedKVPair<K, V>> {
+        std::mem::replace(self, Slot::Empty).into_occupied()
+    }
+}
 
 pub struct SimpleHashTable<K: HashableKey, V: HashValue, H: Hasher<K>> {
-    data: VecS<K, V>,
-    deleted: BitSet,
+    data: Vec<Slot<K, V>>,
+    // With incremental resizing, the array being migrated out of and how far its
+    // migration got. Entries at or past `migrated` may still live in `old`.
+    old: Vec<Slot<K, V>>,
+    migrated: usize,
     slots_used: usize,
     deleted_slots: usize,
+    resize_count: usize,
+    // Bumped whenever entries may move, invalidating SlotHandles.
+    generation: u64,
+    next_stamp: u64,
+    pop_cursor: usize,
+    config: TableConfig,
     ph_1: PhantomData<H>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TableConfig {
+    max_load_factor: f64,
+    growth_factor: f64,
+    tombstone_threshold: f64,
+    capacity_policy: CapacityPolicy,
+    // Slots of the old array migrated per operation; None rehashes all at once.
+    migration_batch: Option<usize>,
+    // Live load under which removals rebuild into a smaller array; None never
+    // shrinks automatically.
+    shrink_threshold: Option<f64>,
+    min_capacity: usize,
+}
+
+impl Default for TableConfig {
+    fn default() -> Self {
+        TableConfig {
+            max_load_factor: DEFAULT_MAX_LOAD_FACTOR,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
+            tombstone_threshold: DEFAULT_TOMBSTONE_THRESHOLD,
+            capacity_policy: CapacityPolicy::default(),
+            migration_batch: None,
+            shrink_threshold: None,
+            min_capacity: DEFAULT_CAPACITY,
+        }
+    }
+}
+
 impl<K: HashableKey, V: HashValue, H: Hasher<K>> SimpleHashTable<K, V, H> {
     fn simple_resizer(&mut self, next_capacity: usize) -> bool {
-        let mut new_data = Vec::<S<K, V>>::with_capacity(next_capacity);
-        new_data.resize_with(next_capacity, || None);
-        for i in 0..self.data.len() {
-            let element = &mut self.data[i];
-            if !element.is_none() && !self.deleted.contains(i) {
-                let kv = element.as_mut().unwrap();
-                let hashed = H::hash(&kv.key);
-                for i in 0..next_capacity {
-                    let real_pos = (hashed + i) % next_capacity;
-                    let element = &mut new_data[real_pos];
-                    if element.is_some() {
-                        continue;
-                    }
-                    let owned_kv = std::mem::replace(kv, Default::default());
-                    element.replace(owned_kv);
-                    break;
-                }
-            }
+        self.try_resize(next_capacity).is_ok()
+    }
+
+    fn try_resize(&mut self, next_capacity: usize) -> Result<(), HashTableError> {
+        // Allocate first, so a failed resize leaves a pending migration alone.
+        let new_data = self.try_alloc_slots(next_capacity)?;
+        self.finish_migration();
+        let next_capacity = new_data.len();
+        let policy = self.config.capacity_policy;
+        let mut new_data = new_data;
+        for kv in std::mem::take(&mut self.data)
+            .into_iter()
+            .filter_map(Slot::into_occupied)
+        {
+            let home = policy.home_slot(kv.hash, next_capacity);
+            let free_pos = (0..next_capacity)
+                .map(|step| policy.wrap(home + step, next_capacity))
+                .find(|&pos| matches!(new_data[pos], Slot::Empty))
+                .expect("resized table has room for every entry");
+            new_data[free_pos] = Slot::Occupied(kv);
         }
-        self.deleted.clear();
-        self.deleted.reserve_len(next_capacity);
         self.data = new_data;
         self.deleted_slots = 0;
+        self.pop_cursor = 0;
+        self.resize_count += 1;
+        self.generation += 1;
 
-        true
+        Ok(())
     }
 
-    fn get_pos<'a>(&self, key: &'a K) -> Option<usize> {
-        let hashed = H::hash(&key);
-        let total_slots = self.deleted_slots + self.slots_used;
-        for i in 0..total_slots {
-            let real_pos = (hashed + i) % self.data.len();
-            let element = &self.data[real_pos];
-            if element.is_none() {
-                return None;
-            }
-            if self.deleted.contains(real_pos) {
-                continue;
+    // Like `try_resize`, but only swaps in the new array; the entries move over
+    // a batch at a time in later operations. Growth waits while a migration is
+    // pending, and every insert moves at least one entry, so the new array has to
+    // hold the migrating entries plus as many inserts again.
+    fn try_start_migration(&mut self, next_capacity: usize) -> Result<(), HashTableError> {
+        debug_assert!(!self.is_migrating());
+        let required = self
+            .slots_used
+            .checked_mul(2)
+            .and_then(|n| n.checked_add(2))
+            .ok_or(HashTableError::CapacityOverflow)?;
+        let new_data = self.try_alloc_slots(std::cmp::max(next_capacity, required))?;
+        self.old = std::mem::replace(&mut self.data, new_data);
+        self.migrated = 0;
+        self.deleted_slots = 0;
+        self.pop_cursor = 0;
+        self.resize_count += 1;
+        self.generation += 1;
+        Ok(())
+    }
+
+    fn try_rehash(&mut self, next_capacity: usize) -> Result<(), HashTableError> {
+        match self.config.migration_batch {
+            Some(_) => self.try_start_migration(next_capacity),
+            None => self.try_resize(next_capacity),
+        }
+    }
+
+    fn try_alloc_slots(&self, next_capacity: usize) -> Result<Vec<Slot<K, V>>, HashTableError> {
+        let next_capacity = self
+            .config
+            .capacity_policy
+            .round(next_capacity)
+            .ok_or(HashTableError::CapacityOverflow)?;
+        let slot_size = std::mem::size_of::<Slot<K, V>>();
+        match next_capacity.checked_mul(slot_size) {
+            Some(bytes) if bytes <= isize::MAX as usize => {}
+            _ => return Err(HashTableError::CapacityOverflow),
+        }
+        if next_capacity < self.slots_used {
+            return Err(HashTableError::CapacityTooSmall {
+                capacity: next_capacity,
+                entries: self.slots_used,
+            });
+        }
+        let mut new_data: Vec<Slot<K, V>> = Vec::new();
+        if new_data.try_reserve_exact(next_capacity).is_err() {
+            return Err(HashTableError::AllocError {
+                capacity: next_capacity,
+            });
+        }
+        new_data.resize_with(next_capacity, || Slot::Empty);
+        Ok(new_data)
+    }
+
+    pub fn is_migrating(&self) -> bool {
+        !self.old.is_empty()
+    }
+
+    // Moves up to `budget` entries from the old array into `data`, starting at
+    // `migrated`. Empty slots and tombstones along the way don't count.
+    fn migrate(&mut self, budget: usize) {
+        let mut end = self.migrated;
+        let mut moved = 0;
+        while moved < budget && end < self.old.len() {
+            // Migrated slots become tombstones so probes for the rest still work.
+            if let Some(kv) = std::mem::replace(&mut self.old[end], Slot::Tombstone).into_occupied()
+            {
+                self.place_migrated(kv);
+                moved += 1;
             }
-            let unwrapped = element.as_ref().unwrap();
-            if unwrapped.key == *key {
-                return Some(real_pos);
+            end += 1;
+        }
+        if end > self.migrated {
+            self.generation += 1;
+        }
+        self.migrated = end;
+        if self.migrated == self.old.len() {
+            self.old = Vec::new();
+            self.migrated = 0;
+        }
+    }
+
+    fn finish_migration(&mut self) {
+        self.migrate(usize::MAX);
+    }
+
+    fn place_migrated(&mut self, kv: HashedKVPair<K, V>) {
+        #[cfg(test)]
+        tests::count_migrated_entry();
+        let pos = self
+            .get_free_pos_for_hash(kv.hash)
+            .expect("new array has room for every migrating entry");
+        if matches!(self.data[pos], Slot::Tombstone) {
+            self.deleted_slots -= 1;
+        }
+        self.data[pos] = Slot::Occupied(kv);
+    }
+
+    fn find_old<F: Fn(&K) -> bool>(&self, hash: usize, is_match: F) -> Option<usize> {
+        let capacity = self.old.len();
+        let policy = self.config.capacity_policy;
+        let home = policy.home_slot(hash, capacity);
+        for step in 0..capacity {
+            let pos = policy.wrap(home + step, capacity);
+            match &self.old[pos] {
+                Slot::Empty => return None,
+                Slot::Tombstone => {}
+                Slot::Occupied(kv) => {
+                    if kv.hash == hash && is_match(&kv.key) {
+                        return Some(pos);
+                    }
+                }
             }
         }
         None
     }
-}
 
-impl<K: HashableKey, V: HashValue, H: Hasher<K>> HashTable<K, V> for SimpleHashTable<K, V, H> {
-    fn insert(&mut self, key: K, value: V) {
-        {
-            let found_pos = self.get_pos(&key);
-            if found_pos.is_some() {
-                self.data[found_pos.unwrap()] = Some(KVPair {
-                    key: key,
-                    value: value,
-                });
-                return;
+    // Looks the key up in both arrays while a migration is pending.
+    fn lookup<F: Fn(&K) -> bool>(&self, hash: usize, is_match: F) -> Option<&HashedKVPair<K, V>> {
+        if let ProbeResult::Found(pos) = self.find_slot_with_hash(hash, &is_match) {
+            return self.data[pos].occupied();
+        }
+        if self.old.is_empty() {
+            return None;
+        }
+        self.old[self.find_old(hash, is_match)?].occupied()
+    }
+
+    // Like `find_slot_with_hash`, but first pulls a matching entry out of the old
+    // array and migrates the rest of this operation's batch, so the caller only
+    // has to deal with `data`. At most a batch of entries moves per call.
+    fn find_slot_mut<F: Fn(&K) -> bool>(&mut self, hash: usize, is_match: F) -> ProbeResult {
+        self.advance_migration(hash, &is_match);
+        self.find_slot_with_hash(hash, is_match)
+    }
+
+    fn advance_migration<F: Fn(&K) -> bool>(&mut self, hash: usize, is_match: F) {
+        let Some(batch) = self.config.migration_batch.filter(|_| self.is_migrating()) else {
+            return;
+        };
+        let mut budget = batch;
+        if let Some(pos) = self.find_old(hash, is_match) {
+            let kv = std::mem::replace(&mut self.old[pos], Slot::Tombstone).into_occupied();
+            self.place_migrated(kv.unwrap());
+            self.generation += 1;
+            budget -= 1;
+        }
+        self.migrate(budget);
+    }
+
+    fn get_pos_mut<Q>(&mut self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        match self.find_slot_mut(H::hash(key), |k| k.borrow() == key) {
+            ProbeResult::Found(pos) => Some(pos),
+            ProbeResult::Vacant(_) => None,
+        }
+    }
+
+    fn find_slot_with_hash<F: Fn(&K) -> bool>(&self, hash: usize, is_match: F) -> ProbeResult {
+        let mut reusable = None;
+        for (pos, state) in self.probe_iter(hash) {
+            match state {
+                SlotState::Empty => return ProbeResult::Vacant(reusable.or(Some(pos))),
+                SlotState::Tombstone => {
+                    reusable.get_or_insert(pos);
+                }
+                SlotState::Occupied => {
+                    let kv = self.data[pos].occupied().unwrap();
+                    if kv.hash == hash && is_match(&kv.key) {
+                        return ProbeResult::Found(pos);
+                    }
+                }
             }
         }
+        ProbeResult::Vacant(reusable)
+    }
+
+    pub fn probe_iter(&self, hash: usize) -> ProbeIter<'_, K, V> {
+        #[cfg(test)]
+        tests::count_probe_pass();
+        let capacity = self.data.len();
+        ProbeIter {
+            data: &self.data,
+            start: self.config.capacity_policy.home_slot(hash, capacity),
+            policy: self.config.capacity_policy,
+            step: 0,
+            done: false,
+        }
+    }
 
+    fn try_grow_if_needed(&mut self) -> Result<bool, HashTableError> {
+        if self.is_migrating() {
+            return Ok(false);
+        }
         let current_capacity = self.data.len();
-        let total_used = self.slots_used + self.deleted_slots;
-        if total_used >= current_capacity {
-            let next_capacity = current_capacity * 2 + 1;
-            if !self.simple_resizer(next_capacity) {
-                panic!(
-                    "couldn't resize from {} to {}",
-                    current_capacity, next_capacity
-                );
-            }
+        let mut resized = false;
+        if self.deleted_slots as f64 > current_capacity as f64 * self.config.tombstone_threshold {
+            self.try_rehash(current_capacity)?;
+            resized = true;
         }
         let total_used = self.slots_used + self.deleted_slots;
+        let max_slots = self.max_slots(current_capacity);
+        if total_used >= max_slots {
+            // Mostly tombstones: rehashing in place frees enough room without growing.
+            let next_capacity = if self.slots_used < max_slots / 2 {
+                current_capacity
+            } else {
+                self.config
+                    .capacity_policy
+                    .grown(current_capacity, self.config.growth_factor)
+                    .ok_or(HashTableError::CapacityOverflow)?
+            };
+            self.try_rehash(next_capacity)?;
+            resized = true;
+        }
+        Ok(resized)
+    }
+
+    // Makes room for one more entry and returns the slot it should go in. `vacant`
+    // is the reusable slot found by `find_slot_with_hash`, which a resize invalidates.
+    fn try_claim_slot(
+        &mut self,
+        hash: usize,
+        vacant: Option<usize>,
+    ) -> Result<usize, HashTableError> {
+        let resized = self.try_grow_if_needed()?;
+        match vacant {
+            Some(pos) if !resized => Ok(pos),
+            _ => Ok(self
+                .get_free_pos_for_hash(hash)
+                .expect("no free slot available after growing")),
+        }
+    }
+
+    fn claim_slot(&mut self, hash: usize, vacant: Option<usize>) -> usize {
         let current_capacity = self.data.len();
+        match self.try_claim_slot(hash, vacant) {
+            Ok(pos) => pos,
+            Err(err) => panic!("couldn't resize from {}: {}", current_capacity, err),
+        }
+    }
 
-        let hashed = H::hash(&key);
-        for i in 0..(total_used + 1) {
-            let curr_pos = (hashed + i) % current_capacity;
-            let element = &self.data[curr_pos];
-            let is_deleted = self.deleted.contains(curr_pos);
-            if element.is_some() && !is_deleted {
-                continue;
-            }
-            if is_deleted {
-                self.deleted.remove(curr_pos);
-            }
-            self.data[curr_pos] = Some(KVPair {
-                key: key,
-                value: value,
-            });
-            self.slots_used += 1;
-            break;
+    fn max_slots(&self, capacity: usize) -> usize {
+        (capacity as f64 * self.config.max_load_factor) as usize
+    }
+
+    fn capacity_for(&self, entries: usize) -> usize {
+        if self.config.max_load_factor >= 1.0 {
+            return entries;
+        }
+        let mut capacity = (entries as f64 / self.config.max_load_factor).ceil() as usize;
+        while self.max_slots(capacity) < entries {
+            capacity += 1;
         }
+        capacity
     }
-    fn delete(&mut self, key: &K) {
-        let hashed = H::hash(&key);
-        let slots_to_check = self.slots_used + self.deleted_slots;
-        for i in 0..slots_to_check {
-            let curr = (hashed + i) % self.data.len();
-            let element = &mut self.data[curr];
-            if element.is_none() {
-                return; // not found
-            }
-            if self.deleted.contains(curr) {
-                continue;
-            }
-            let unwrapped = element.as_mut().unwrap();
-            if unwrapped.key == *key {
-                // found
-                self.deleted.insert(curr);
-                self.slots_used -= 1;
-                self.deleted_slots += 1;
-                element.take();
-                return;
-            }
-            // have to continue checking
+
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(err) = self.try_reserve(additional) {
+            panic!("couldn't reserve {} additional slots: {}", additional, err);
         }
     }
 
-    fn has(&self, key: &K) -> bool {
-        let hashed = H::hash(&key);
-        let total_slots = self.deleted_slots + self.slots_used;
-        for i in 0..total_slots {
-            let real_pos = (hashed + i) % self.data.len();
-            let element = &self.data[real_pos];
-            if element.is_none() {
-                return false;
-            }
-            if self.deleted.contains(real_pos) {
-                continue;
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), HashTableError> {
+        let current_capacity = self.data.len();
+        let total_used = self.slots_used + self.deleted_slots;
+        let needed = total_used
+            .checked_add(additional)
+            .ok_or(HashTableError::CapacityOverflow)?;
+        if needed > self.max_slots(current_capacity) {
+            let required = self
+                .slots_used
+                .checked_add(additional)
+                .ok_or(HashTableError::CapacityOverflow)?;
+            let required_capacity = self.capacity_for(required);
+            self.try_resize(std::cmp::max(required_capacity, current_capacity))?;
+        }
+        Ok(())
+    }
+
+    pub fn try_grow(&mut self, new_capacity: usize) -> Result<(), HashTableError> {
+        if new_capacity <= self.data.len() {
+            return Ok(());
+        }
+        self.try_resize(new_capacity)
+    }
+
+    pub fn try_insert_or_grow(&mut self, key: K, value: V) -> Result<Option<V>, HashTableError> {
+        let hash = H::hash(&key);
+        match self.find_slot_mut(hash, |k| *k == key) {
+            ProbeResult::Found(pos) => {
+                let kv = self.data[pos].occupied_mut().unwrap();
+                Ok(Some(std::mem::replace(&mut kv.value, value)))
             }
-            let unwrapped = element.as_ref().unwrap();
-            if unwrapped.key == *key {
-                return true;
+            ProbeResult::Vacant(vacant) => {
+                let pos = self.try_claim_slot(hash, vacant)?;
+                self.place_at(pos, hash, key, value);
+                Ok(None)
             }
         }
-        false
     }
 
-    fn get<'a>(&self, key: &'a K) -> Option<V> {
-        let hashed = H::hash(&key);
-        let total_slots = self.deleted_slots + self.slots_used;
-        for i in 0..total_slots {
-            let real_pos = (hashed + i) % self.data.len();
-            let element = &self.data[real_pos];
-            if element.is_none() {
-                return None;
+    pub fn for_entries(entries: usize) -> Self {
+        let config = TableConfig::default();
+        let mut table = create_configured_hash_table(0, config);
+        let capacity = std::cmp::max(table.capacity_for(entries), 1).next_power_of_two();
+        table.data.resize_with(capacity, || Slot::Empty);
+        table
+    }
+
+    pub fn max_load_factor(&self) -> f64 {
+        self.config.max_load_factor
+    }
+
+    pub fn set_max_load_factor(&mut self, max_load_factor: f64) {
+        assert!(
+            valid_max_load_factor(max_load_factor),
+            "max load factor {} is not in (0, 1)",
+            max_load_factor
+        );
+        self.config.max_load_factor = max_load_factor;
+    }
+
+    pub fn builder() -> SimpleHashTableBuilder<K, V, H> {
+        SimpleHashTableBuilder::new()
+    }
+
+    pub fn tombstone_threshold(&self) -> f64 {
+        self.config.tombstone_threshold
+    }
+
+    pub fn capacity_policy(&self) -> CapacityPolicy {
+        self.config.capacity_policy
+    }
+
+    pub fn set_tombstone_threshold(&mut self, threshold: f64) {
+        assert!(
+            valid_tombstone_threshold(threshold),
+            "tombstone threshold {} is not in (0, 1]",
+            threshold
+        );
+        self.config.tombstone_threshold = threshold;
+    }
+
+    pub fn resize_count(&self) -> usize {
+        self.resize_count
+    }
+
+    // Growing once for the whole batch avoids the log2(n / capacity) intermediate
+    // rehashes that inserting one pair at a time goes through.
+    pub fn insert_many<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        let pairs = pairs.into_iter();
+        self.reserve(pairs.size_hint().0);
+        for (key, value) in pairs {
+            self.insert(key, value);
+        }
+    }
+
+    // Skips the lookup for an existing entry, so inserting a key that is already
+    // present leaves two entries for it.
+    pub fn insert_unique_unchecked(&mut self, key: K, value: V) {
+        let hash = H::hash(&key);
+        self.advance_migration(hash, |_| false);
+        let pos = self.claim_slot(hash, None);
+        self.place_at(pos, hash, key, value);
+    }
+
+    pub fn insert_with_handle(&mut self, key: K, value: V) -> (Option<V>, SlotHandle) {
+        let hash = H::hash(&key);
+        let (pos, previous) = match self.find_slot_mut(hash, |k| *k == key) {
+            ProbeResult::Found(pos) => {
+                let kv = self.data[pos].occupied_mut().unwrap();
+                (pos, Some(std::mem::replace(&mut kv.value, value)))
             }
-            if self.deleted.contains(real_pos) {
-                continue;
+            ProbeResult::Vacant(vacant) => {
+                let pos = self.claim_slot(hash, vacant);
+                self.place_at(pos, hash, key, value);
+                (pos, None)
             }
-            let unwrapped = element.as_ref().unwrap();
-            if unwrapped.key == *key {
-                return Some(unwrapped.value.clone());
+        };
+        (previous, self.handle_at(pos))
+    }
+
+    pub fn handle_of<Q>(&self, key: &Q) -> Option<SlotHandle>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let hash = H::hash(key);
+        let is_match = |k: &K| k.borrow() == key;
+        match self.find_slot_with_hash(hash, is_match) {
+            ProbeResult::Found(pos) => Some(self.handle_at(pos)),
+            ProbeResult::Vacant(_) if self.is_migrating() => {
+                let pos = self.find_old(hash, is_match)?;
+                Some(self.handle_at(self.data.len() + pos))
             }
+            ProbeResult::Vacant(_) => None,
         }
-        None
     }
 
-    fn size(&self) -> usize {
-        self.slots_used
+    // `index` must hold an entry; indexes past the end of `data` point into the
+    // old array.
+    fn handle_at(&self, index: usize) -> SlotHandle {
+        let slot = match index.checked_sub(self.data.len()) {
+            Some(old_index) => &self.old[old_index],
+            None => &self.data[index],
+        };
+        SlotHandle {
+            index,
+            generation: self.generation,
+            stamp: slot.occupied().unwrap().stamp,
+        }
     }
 
-    fn capacity(&self) -> usize {
-        self.data.len()
+    pub fn get_by_handle(&self, handle: SlotHandle) -> Option<&V> {
+        if handle.generation != self.generation {
+            return None;
+        }
+        let slot = match handle.index.checked_sub(self.data.len()) {
+            Some(old_index) => self.old.get(old_index)?,
+            None => &self.data[handle.index],
+        };
+        slot.occupied()
+            .filter(|kv| kv.stamp == handle.stamp)
+            .map(|kv| &kv.value)
     }
 
-    fn wasted_capacity(&self) -> usize {
-        self.deleted_slots
+    pub fn get_by_handle_mut(&mut self, handle: SlotHandle) -> Option<&mut V> {
+        if handle.generation != self.generation {
+            return None;
+        }
+        let slot = match handle.index.checked_sub(self.data.len()) {
+            Some(old_index) => self.old.get_mut(old_index)?,
+            None => &mut self.data[handle.index],
+        };
+        slot.occupied_mut()
+            .filter(|kv| kv.stamp == handle.stamp)
+            .map(|kv| &mut kv.value)
     }
-}
-
-pub fn create_simple_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>>(
-    capacity: usize,
-) -> SimpleHashTable<K, V, H> {
-    let mut data = Vec::<S<K, V>>::with_capacity(capacity);
-    data.resize_with(capacity, || None);
 
-    SimpleHashTable {
-        data: data,
-        deleted: BitSet::with_capacity(capacity),
-        slots_used: 0,
-        deleted_slots: 0,
-        ph_1: Default::default(),
+    pub fn shrink_to_fit(&mut self) {
+        let current_capacity = self.data.len();
+        let next_capacity = std::cmp::max(self.capacity_for(self.slots_used), 1);
+        if !self.simple_resizer(next_capacity) {
+            panic!(
+                "couldn't resize from {} to {}",
+                current_capacity, next_capacity
+            );
+        }
     }
-}
 
-struct SimpleHasher;
-impl Hasher<i64> for SimpleHasher {
-    fn hash(key: &i64) -> usize {
-        *key as usize
+    fn get_free_pos_for_hash(&self, hashed: usize) -> Option<usize> {
+        self.probe_iter(hashed)
+            .find(|(_, state)| *state != SlotState::Occupied)
+            .map(|(pos, _)| pos)
     }
-}
 
-impl HashableKey for i64 {}
-impl HashValue for i64 {}
+    fn place_at(&mut self, pos: usize, hash: usize, key: K, value: V) {
+        let stamp = self.next_stamp;
+        self.next_stamp += 1;
+        let previous = std::mem::replace(
+            &mut self.data[pos],
+            Slot::Occupied(HashedKVPair {
+                hash,
+                key,
+                value,
+                stamp,
+            }),
+        );
+        if matches!(previous, Slot::Tombstone) {
+            self.deleted_slots -= 1;
+        }
+        self.slots_used += 1;
+    }
 
-type IntegerToIntegerHT = SimpleHashTable<i64, i64, SimpleHasher>;
-fn create_integer_to_integer_ht() -> IntegerToIntegerHT {
-    return create_simple_hash_table(32);
-}
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn take_at(&mut self, pos: usize) -> HashedKVPair<K, V> {
+        self.slots_used -= 1;
+        self.deleted_slots += 1;
+        std::mem::replace(&mut self.data[pos], Slot::Tombstone)
+            .into_occupied()
+            .unwrap()
+    }
 
-    #[test]
-    fn it_works() {
-        let mut ht: SimpleHashTable<i64, i64, SimpleHasher> = create_integer_to_integer_ht();
-        assert_eq!(ht.size(), 0);
-        ht.insert(25, 32);
-        assert_eq!(ht.size(), 1);
-        ht.insert(25, 32);
-        assert_eq!(ht.size(), 1);
-        ht.insert(25, 32);
-        assert_eq!(ht.size(), 1);
-        ht.insert(25, 32);
-        assert_eq!(ht.size(), 1);
-        ht.insert(25, 32);
-        assert_eq!(ht.size(), 1);
-        let expected = Some(32i64);
-        assert_eq!(ht.has(&25), true);
-        assert_eq!(ht.size(), 1);
-        assert_eq!(ht.get(&25), expected);
-        assert_eq!(ht.size(), 1);
-        assert_eq!(ht.get(&25), expected);
-        ht.insert(26, 32);
-        assert_eq!(ht.size(), 2);
-        ht.insert(26, 32);
-        assert_eq!(ht.size(), 2);
-        ht.insert(26, 32);
-        assert_eq!(ht.size(), 2);
-        ht.insert(26, 32);
-        assert_eq!(ht.size(), 2);
-        ht.insert(26, 33);
-        assert_eq!(ht.size(), 2);
-        assert_eq!(*ht.get(&26).as_ref().unwrap(), 33);
-        ht.delete(&26);
-        assert_eq!(ht.size(), 1);
-        assert!(!ht.has(&26))
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos_mut(key)?;
+        let kv = self.take_at(pos);
+        self.shrink_if_needed();
+        Some(kv.value)
     }
 
-    #[test]
-    fn overflow_test() {
-        let mut ht = create_integer_to_integer_ht();
-        let sz_check: i64 = 1000000;
-        for i in 0..sz_check {
-            ht.insert(i, i);
-            assert_eq!(ht.size(), (i + 1) as usize);
+    // The target capacity leaves the table at most half as loaded as the growth
+    // threshold allows, and the builder keeps the shrink threshold below that,
+    // so a shrink is never undone by the next few inserts or vice versa.
+    fn shrink_if_needed(&mut self) {
+        let Some(threshold) = self.config.shrink_threshold else {
+            return;
+        };
+        let capacity = self.data.len();
+        if self.is_migrating()
+            || capacity <= self.config.min_capacity
+            || self.slots_used as f64 >= capacity as f64 * threshold
+        {
+            return;
+        }
+        let target = std::cmp::max(
+            self.capacity_for(self.slots_used.saturating_mul(2)),
+            self.config.min_capacity,
+        );
+        match self.config.capacity_policy.round(target) {
+            // A failed shrink just leaves the table as it was.
+            Some(target) if target < capacity => {
+                let _ = self.try_rehash(target);
+            }
+            _ => {}
         }
-        assert_eq!(ht.size(), sz_check as usize);
     }
 
-    #[test]
-    fn overflow_delete_test() {
-        let mut ht = create_integer_to_integer_ht();
-        let sz_check: i64 = 1000000;
-        for i in 0..sz_check {
-            ht.insert(i, i);
-            ht.delete(&i);
-            assert_eq!(ht.size(), 0 as usize);
-        }
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.get(key).is_some()
     }
 
-    #[test]
-    fn overflow_random_test() {
-        let mut ht = create_integer_to_integer_ht();
-        let sz_check: i64 = 1000;
-        let mut hset: HashSet<i64> = HashSet::new();
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
 
-        for i in 0..sz_check {
-            let mut num: i64;
-            loop {
-                num = rand::thread_rng().gen_range(-1_000_000_000_000..1_000_000_000_000);
-                if hset.insert(num) {
-                    break;
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos_mut(key)?;
+        self.data[pos].occupied_mut().map(|kv| &mut kv.value)
+    }
+
+    pub fn delete<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.remove(key);
+    }
+
+    pub fn get_many_mut<const N: usize>(&mut self, keys: [&K; N]) -> Option<[&mut V; N]> {
+        let mut positions = [0usize; N];
+        for (i, key) in keys.iter().enumerate() {
+            positions[i] = self.get_pos_mut(*key)?;
+        }
+        let slots = self.data.get_disjoint_mut(positions).ok()?;
+        Some(slots.map(|element| &mut element.occupied_mut().unwrap().value))
+    }
+
+    // Hashes every key up front, so the home slots of the next few keys can be
+    // prefetched while the current one is looked up.
+    pub fn get_batch<'a>(&'a self, keys: &[K]) -> Vec<Option<&'a V>> {
+        let hashes: Vec<usize> = keys.iter().map(|key| H::hash(key)).collect();
+        let capacity = self.data.len();
+        keys.iter()
+            .zip(hashes.iter())
+            .enumerate()
+            .map(|(i, (key, &hash))| {
+                if let Some(&ahead) = hashes.get(i + BATCH_PREFETCH_DISTANCE) {
+                    let home = self.config.capacity_policy.home_slot(ahead, capacity);
+                    prefetch::prefetch_read(self.data.as_ptr().wrapping_add(home));
                 }
+                self.lookup(hash, |k| k == key).map(|kv| &kv.value)
+            })
+            .collect()
+    }
+
+    pub fn has_all<'a, I>(&self, keys: I) -> bool
+    where
+        I: IntoIterator<Item = &'a K>,
+        K: 'a,
+    {
+        keys.into_iter().all(|key| self.has(key))
+    }
+
+    pub fn missing_keys<'a, I>(&self, keys: I) -> Vec<&'a K>
+    where
+        I: IntoIterator<Item = &'a K>,
+        K: 'a,
+    {
+        keys.into_iter().filter(|key| !self.has(*key)).collect()
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let kv = self.lookup(H::hash(key), |k| k.borrow() == key)?;
+        Some((&kv.key, &kv.value))
+    }
+
+    pub fn take<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.remove(key)
+    }
+
+    pub fn replace_key(&mut self, old: &K, new: K) -> Result<(), ReplaceKeyError> {
+        let old_pos = self.get_pos_mut(old).ok_or(ReplaceKeyError::MissingKey)?;
+        let hash = H::hash(&new);
+        let ProbeResult::Vacant(vacant) = self.find_slot_mut(hash, |k| *k == new) else {
+            return Err(ReplaceKeyError::KeyExists);
+        };
+        let kv = self.take_at(old_pos);
+        let pos = self.claim_slot(hash, vacant);
+        self.place_at(pos, hash, new, kv.value);
+        Ok(())
+    }
+
+    pub fn update<Q, F>(&mut self, key: &Q, f: F) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+        F: FnOnce(&mut V),
+    {
+        match self.get_mut(key) {
+            Some(value) => {
+                f(value);
+                true
             }
-            ht.insert(num, num);
-            for item in hset.iter() {
-                assert!(ht.has(&item));
-            }
-            assert_eq!(ht.size(), (i + 1) as usize);
+            None => false,
+        }
+    }
+
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<&mut V, OccupiedError<K, V>> {
+        let hash = H::hash(&key);
+        let ProbeResult::Vacant(vacant) = self.find_slot_mut(hash, |k| *k == key) else {
+            return Err(OccupiedError { key, value });
+        };
+        let pos = self.claim_slot(hash, vacant);
+        self.place_at(pos, hash, key, value);
+        Ok(&mut self.data[pos].occupied_mut().unwrap().value)
+    }
+
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        self.entry(key).or_insert_with(default)
+    }
+
+    pub fn merge(&mut self, other: SimpleHashTable<K, V, H>, policy: MergePolicy) {
+        match policy {
+            MergePolicy::KeepSelf => self.merge_with(other, |_, mine, _| mine),
+            MergePolicy::KeepOther => self.merge_with(other, |_, _, theirs| theirs),
+        }
+    }
+
+    pub fn merge_with<F: FnMut(&K, V, V) -> V>(
+        &mut self,
+        other: SimpleHashTable<K, V, H>,
+        mut f: F,
+    ) {
+        self.reserve(other.size());
+        for (key, value) in other {
+            let hash = H::hash(&key);
+            match self.find_slot_mut(hash, |k| *k == key) {
+                ProbeResult::Found(pos) => {
+                    // A tombstone while `f` runs, so if it panics the entry is
+                    // gone but the probe chains and counters stay consistent.
+                    let kv = self.take_at(pos);
+                    let value = f(&kv.key, kv.value, value);
+                    self.place_at(pos, hash, kv.key, value);
+                }
+                ProbeResult::Vacant(vacant) => {
+                    let pos = self.claim_slot(hash, vacant);
+                    self.place_at(pos, hash, key, value);
+                }
+            }
+        }
+    }
+
+    pub fn append(&mut self, other: &mut SimpleHashTable<K, V, H>) {
+        self.insert_many(other.drain());
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            slots: self.data.iter().chain(self.old[self.migrated..].iter()),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn slots(&self) -> Slots<'_, K, V, H> {
+        Slots {
+            slots: self.data.iter().enumerate(),
+            capacity: self.data.len(),
+            policy: self.config.capacity_policy,
+            ph_1: PhantomData,
+        }
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.memory_usage_with(|_, _| 0)
+    }
+
+    // `owned_bytes` estimates the heap each entry's key and value own beyond
+    // their inline size, e.g. a String's capacity.
+    pub fn memory_usage_with<F: FnMut(&K, &V) -> usize>(&self, mut owned_bytes: F) -> MemoryUsage {
+        let slot_size = std::mem::size_of::<Slot<K, V>>();
+        MemoryUsage {
+            slot_bytes: self.data.capacity() * slot_size,
+            migrating_bytes: self.old.capacity() * slot_size,
+            owned_bytes: self.iter().map(|(k, v)| owned_bytes(k, v)).sum(),
+        }
+    }
+
+    // One pass over `data`; during an incremental resize the entries still in
+    // the old array aren't included.
+    pub fn probe_stats(&self) -> ProbeStats {
+        let mut stats = ProbeStats::default();
+        let mut total = 0;
+        let mut run = 0;
+        let mut leading_run = None;
+        for slot in self.slots() {
+            let Some(distance) = slot.probe_distance else {
+                leading_run.get_or_insert(run);
+                run = 0;
+                continue;
+            };
+            run += 1;
+            stats.longest_run = std::cmp::max(stats.longest_run, run);
+            stats.min = match stats.entries {
+                0 => distance,
+                _ => std::cmp::min(stats.min, distance),
+            };
+            stats.max = std::cmp::max(stats.max, distance);
+            stats.histogram[std::cmp::min(distance, PROBE_HISTOGRAM_BUCKETS - 1)] += 1;
+            stats.entries += 1;
+            total += distance;
+        }
+        // a run at the end continues into the one at the start
+        if let Some(leading_run) = leading_run {
+            stats.longest_run = std::cmp::max(stats.longest_run, run + leading_run);
+        }
+        if stats.entries > 0 {
+            stats.mean = total as f64 / stats.entries as f64;
+        }
+        stats
+    }
+
+    pub fn debug_validate(&self) -> Result<(), InvariantViolation> {
+        let mut occupied = 0;
+        let mut tombstones = 0;
+        for (index, slot) in self.data.iter().enumerate() {
+            let kv = match slot {
+                Slot::Occupied(kv) => kv,
+                Slot::Tombstone => {
+                    tombstones += 1;
+                    continue;
+                }
+                Slot::Empty => continue,
+            };
+            occupied += 1;
+            if kv.hash != H::hash(&kv.key) {
+                return Err(InvariantViolation::StaleHash { index });
+            }
+            let found = self.find_slot_with_hash(kv.hash, |k| std::ptr::eq(k, &kv.key));
+            if found != ProbeResult::Found(index) {
+                return Err(InvariantViolation::UnreachableKey { index });
+            }
+        }
+        // entries still waiting to migrate, indexed past the end of `data`
+        for (old_index, slot) in self.old.iter().enumerate() {
+            let Slot::Occupied(kv) = slot else {
+                continue;
+            };
+            let index = self.data.len() + old_index;
+            occupied += 1;
+            if kv.hash != H::hash(&kv.key) {
+                return Err(InvariantViolation::StaleHash { index });
+            }
+            let found = self.find_old(kv.hash, |k| std::ptr::eq(k, &kv.key));
+            if old_index < self.migrated || found != Some(old_index) {
+                return Err(InvariantViolation::UnreachableKey { index });
+            }
+        }
+        if occupied != self.slots_used {
+            return Err(InvariantViolation::SlotsUsedMismatch {
+                recorded: self.slots_used,
+                actual: occupied,
+            });
+        }
+        if tombstones != self.deleted_slots {
+            return Err(InvariantViolation::DeletedSlotsMismatch {
+                recorded: self.deleted_slots,
+                actual: tombstones,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    pub fn into_keys(self) -> IntoKeys<K, V> {
+        IntoKeys {
+            inner: self.into_iter(),
+        }
+    }
+
+    pub fn into_values(self) -> IntoValues<K, V> {
+        IntoValues {
+            inner: self.into_iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        self.finish_migration();
+        IterMut {
+            slots: self.data.iter_mut(),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut {
+            inner: self.iter_mut(),
+        }
+    }
+
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        self.finish_migration();
+        let remaining = self.slots_used;
+        self.slots_used = 0;
+        self.deleted_slots = 0;
+        self.generation += 1;
+        Drain {
+            slots: self.data.iter_mut(),
+            remaining,
+        }
+    }
+
+    pub fn extract_if<F: FnMut(&K, &mut V) -> bool>(
+        &mut self,
+        pred: F,
+    ) -> ExtractIf<'_, K, V, H, F> {
+        self.finish_migration();
+        ExtractIf {
+            table: self,
+            pos: 0,
+            pred,
+        }
+    }
+
+    pub fn partition<F: FnMut(&K, &V) -> bool>(mut self, mut pred: F) -> (Self, Self) {
+        self.finish_migration();
+        let mut selected = BitSet::with_capacity(self.data.len());
+        for (i, element) in self.data.iter().enumerate() {
+            if let Slot::Occupied(kv) = element {
+                if pred(&kv.key, &kv.value) {
+                    selected.insert(i);
+                }
+            }
+        }
+        let matched = selected.len();
+        let not_matched = self.slots_used - matched;
+        let mut left = self.empty_like(matched);
+        let mut right = self.empty_like(not_matched);
+        for (i, element) in self.data.iter_mut().enumerate() {
+            if let Some(kv) = element.take() {
+                let target = if selected.contains(i) {
+                    &mut left
+                } else {
+                    &mut right
+                };
+                target.insert(kv.key, kv.value);
+            }
+        }
+        (left, right)
+    }
+
+    fn empty_like(&self, entries: usize) -> Self {
+        create_configured_hash_table(std::cmp::max(self.capacity_for(entries), 1), self.config)
+    }
+
+    pub fn raw_entry(&self) -> RawEntryBuilder<'_, K, V, H> {
+        RawEntryBuilder { table: self }
+    }
+
+    pub fn raw_entry_mut(&mut self) -> RawEntryBuilderMut<'_, K, V, H> {
+        RawEntryBuilderMut { table: self }
+    }
+
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, K, V, H> {
+        self.finish_migration();
+        CursorMut {
+            table: self,
+            pos: 0,
+            current: None,
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        if self.slots_used == 0 {
+            return None;
+        }
+        self.finish_migration();
+        let capacity = self.data.len();
+        for i in 0..capacity {
+            let curr = self
+                .config
+                .capacity_policy
+                .wrap(self.pop_cursor + i, capacity);
+            if !matches!(self.data[curr], Slot::Occupied(_)) {
+                continue;
+            }
+            let kv = self.take_at(curr);
+            self.pop_cursor = self.config.capacity_policy.wrap(curr + 1, capacity);
+            self.shrink_if_needed();
+            return Some((kv.key, kv.value));
+        }
+        None
+    }
+
+    pub fn sorted_iter(&self) -> std::vec::IntoIter<(&K, &V)>
+    where
+        K: Ord,
+    {
+        self.sorted_iter_by(|(a, _), (b, _)| a.cmp(b))
+    }
+
+    pub fn sorted_iter_by<F>(&self, mut cmp: F) -> std::vec::IntoIter<(&K, &V)>
+    where
+        F: FnMut((&K, &V), (&K, &V)) -> std::cmp::Ordering,
+    {
+        let mut entries: Vec<(&K, &V)> = self.iter().collect();
+        entries.sort_by(|a, b| cmp(*a, *b));
+        entries.into_iter()
+    }
+
+    pub fn invert<H2>(&self) -> SimpleHashTable<V, Vec<K>, H2>
+    where
+        K: Clone,
+        V: HashableKey + Clone,
+        H2: Hasher<V>,
+    {
+        let mut inverted = SimpleHashTable::for_entries(self.slots_used);
+        for (key, value) in self.iter() {
+            inverted
+                .entry(value.clone())
+                .or_insert_with(Vec::new)
+                .push(key.clone());
+        }
+        inverted
+    }
+
+    pub fn map_values<V2: HashValue, F: FnMut(&K, V) -> V2>(
+        mut self,
+        mut f: F,
+    ) -> SimpleHashTable<K, V2, H> {
+        self.finish_migration();
+        let data = self
+            .data
+            .into_iter()
+            .map(|element| match element {
+                Slot::Empty => Slot::Empty,
+                Slot::Tombstone => Slot::Tombstone,
+                Slot::Occupied(kv) => {
+                    let value = f(&kv.key, kv.value);
+                    Slot::Occupied(HashedKVPair {
+                        hash: kv.hash,
+                        key: kv.key,
+                        value,
+                        stamp: kv.stamp,
+                    })
+                }
+            })
+            .collect();
+        SimpleHashTable {
+            data,
+            old: Vec::new(),
+            migrated: 0,
+            slots_used: self.slots_used,
+            deleted_slots: self.deleted_slots,
+            resize_count: self.resize_count,
+            generation: self.generation,
+            next_stamp: self.next_stamp,
+            pop_cursor: self.pop_cursor,
+            config: self.config,
+            ph_1: PhantomData,
+        }
+    }
+
+    pub fn min_key(&self) -> Option<&K>
+    where
+        K: Ord,
+    {
+        self.keys().min()
+    }
+
+    pub fn max_key(&self) -> Option<&K>
+    where
+        K: Ord,
+    {
+        self.keys().max()
+    }
+
+    pub fn min_by_key<B: Ord, F: FnMut(&K, &V) -> B>(&self, mut f: F) -> Option<(&K, &V)> {
+        self.iter().min_by_key(|(k, v)| f(k, v))
+    }
+
+    pub fn max_by_key<B: Ord, F: FnMut(&K, &V) -> B>(&self, mut f: F) -> Option<(&K, &V)> {
+        self.iter().max_by_key(|(k, v)| f(k, v))
+    }
+
+    pub fn random_entry<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<(&K, &V)> {
+        if self.slots_used == 0 {
+            return None;
+        }
+        // entries still in the old array would never be sampled
+        let retries = if self.is_migrating() {
+            0
+        } else {
+            RANDOM_ENTRY_RETRIES
+        };
+        for _ in 0..retries {
+            let pos = rng.gen_range(0..self.data.len());
+            if let Slot::Occupied(kv) = &self.data[pos] {
+                return Some((&kv.key, &kv.value));
+            }
+        }
+        // too sparse for rejection sampling, pick the n-th live entry instead
+        let nth = rng.gen_range(0..self.slots_used);
+        self.iter().nth(nth)
+    }
+
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        self.finish_migration();
+        for i in 0..self.data.len() {
+            let keep = match self.data[i].occupied_mut() {
+                Some(kv) => f(&kv.key, &mut kv.value),
+                None => continue,
+            };
+            if !keep {
+                self.take_at(i);
+            }
+        }
+        self.shrink_if_needed();
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, H> {
+        let hash = H::hash(&key);
+        let vacant = match self.find_slot_mut(hash, |k| *k == key) {
+            ProbeResult::Found(pos) => return Entry::Occupied(OccupiedEntry { table: self, pos }),
+            ProbeResult::Vacant(vacant) => vacant,
+        };
+        let pos = self.claim_slot(hash, vacant);
+        Entry::Vacant(VacantEntry {
+            table: self,
+            key,
+            hash,
+            pos,
+        })
+    }
+
+    pub fn entry_ref<'a, 'b, Q>(&'a mut self, key: &'b Q) -> EntryRef<'a, 'b, K, Q, V, H>
+    where
+        K: Borrow<Q> + From<&'b Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let hash = H::hash(key);
+        let vacant = match self.find_slot_mut(hash, |k| k.borrow() == key) {
+            ProbeResult::Found(pos) => {
+                return EntryRef::Occupied(OccupiedEntry { table: self, pos })
+            }
+            ProbeResult::Vacant(vacant) => vacant,
+        };
+        let pos = self.claim_slot(hash, vacant);
+        EntryRef::Vacant(VacantEntryRef {
+            table: self,
+            key,
+            hash,
+            pos,
+        })
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> HashTable<K, V> for SimpleHashTable<K, V, H> {
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let current_capacity = self.data.len();
+        match self.try_insert_or_grow(key, value) {
+            Ok(previous) => previous,
+            Err(err) => panic!("couldn't resize from {}: {}", current_capacity, err),
+        }
+    }
+    fn has(&self, key: &K) -> bool {
+        SimpleHashTable::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        SimpleHashTable::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        SimpleHashTable::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        SimpleHashTable::remove(self, key)
+    }
+
+    fn clear(&mut self) {
+        for element in self.data.iter_mut() {
+            *element = Slot::Empty;
+        }
+        self.old = Vec::new();
+        self.migrated = 0;
+        self.slots_used = 0;
+        self.deleted_slots = 0;
+        self.generation += 1;
+    }
+
+    fn size(&self) -> usize {
+        self.slots_used
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        self.deleted_slots
+    }
+}
+
+// A slot index stamped with the table generation it was taken at and the stamp
+// of the entry it points to. Moving entries (resizing, migrating, clearing)
+// makes older handles stale; removing an entry only stales the handles to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotHandle {
+    index: usize,
+    generation: u64,
+    stamp: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProbeResult {
+    Found(usize),
+    Vacant(Option<usize>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    KeepSelf,
+    KeepOther,
+}
+
+// `None` once the grown capacity no longer fits in a usize.
+fn grown_capacity(capacity: usize, growth_factor: f64) -> Option<usize> {
+    let grown = capacity as f64 * growth_factor;
+    if grown.is_nan() || grown >= usize::MAX as f64 {
+        return None;
+    }
+    (grown as usize).checked_add(1)
+}
+
+// For the power-of-two open-addressing tables: the capacity to rehash to once
+// `used` entries plus `deleted` tombstones reach `max_slots`, or `None` while
+// there's room. Mostly tombstones: rehashing in place frees enough room without
+// growing.
+fn rehash_capacity(
+    capacity: usize,
+    max_slots: usize,
+    used: usize,
+    deleted: usize,
+) -> Option<usize> {
+    if used + deleted < max_slots {
+        return None;
+    }
+    if used < max_slots / 2 {
+        return Some(capacity.max(1));
+    }
+    match capacity.checked_mul(2) {
+        Some(doubled) => Some(doubled.max(1)),
+        None => panic!("couldn't resize from {}: capacity overflow", capacity),
+    }
+}
+
+// How SimpleHashTable rounds a requested capacity, and so how it maps a hash
+// to a slot. Zero capacity stays zero under either policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CapacityPolicy {
+    // Probing masks the position instead of dividing; the hash is scrambled
+    // first since masking keeps only its low bits.
+    #[default]
+    NextPowerOfTwo,
+    // Probing takes the hash modulo a prime, which smooths out weak hashers.
+    NextPrime,
+}
+
+// Primes about 1.2x apart, so rounding up to one wastes little.
+const PRIME_LADDER: [usize; 72] = [
+    3, 7, 11, 17, 23, 29, 37, 47, 59, 71, 89, 107, 131, 163, 197, 239, 293, 353, 431, 521, 631,
+    761, 919, 1103, 1327, 1597, 1931, 2333, 2801, 3371, 4049, 4861, 5839, 7013, 8419, 10103, 12143,
+    14591, 17519, 21023, 25229, 30293, 36353, 43627, 52361, 62851, 75431, 90523, 108631, 130363,
+    156437, 187751, 225307, 270371, 324449, 389357, 467237, 560689, 672827, 807403, 968897,
+    1162687, 1395263, 1674319, 2009191, 2411033, 2893249, 3471899, 4166287, 4999559, 5999471,
+    7199369,
+];
+
+// Deterministic Miller-Rabin; these bases are enough for every u64.
+fn is_prime(n: usize) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    let n = n as u64;
+    if n < 2 {
+        return false;
+    }
+    if let Some(&base) = BASES.iter().find(|&&base| n.is_multiple_of(base)) {
+        return n == base;
+    }
+    let mul_mod = |a: u64, b: u64| (a as u128 * b as u128 % n as u128) as u64;
+    let pow_mod = |mut base: u64, mut exp: u64| {
+        let mut result = 1;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = mul_mod(result, base);
+            }
+            base = mul_mod(base, base);
+            exp >>= 1;
+        }
+        result
+    };
+    let d = (n - 1) >> (n - 1).trailing_zeros();
+    BASES.iter().all(|&base| {
+        let mut x = pow_mod(base, d);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        let mut e = d;
+        while e * 2 < n - 1 {
+            x = mul_mod(x, x);
+            e *= 2;
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+// Smallest ladder prime that fits `capacity`, or past the ladder the first
+// prime at or above it.
+fn next_prime(capacity: usize) -> Option<usize> {
+    if let Some(&prime) = PRIME_LADDER.iter().find(|&&prime| prime >= capacity) {
+        return Some(prime);
+    }
+    (capacity..=usize::MAX).find(|&n| is_prime(n))
+}
+
+impl CapacityPolicy {
+    fn round(self, capacity: usize) -> Option<usize> {
+        if capacity == 0 {
+            return Some(0);
+        }
+        match self {
+            CapacityPolicy::NextPowerOfTwo => capacity.checked_next_power_of_two(),
+            CapacityPolicy::NextPrime => next_prime(capacity),
+        }
+    }
+
+    // Smallest allowed capacity that is at least `capacity * growth_factor` and
+    // larger than `capacity`.
+    fn grown(self, capacity: usize, growth_factor: f64) -> Option<usize> {
+        let grown = grown_capacity(capacity, growth_factor)? - 1;
+        self.round(std::cmp::max(grown, capacity + 1))
+    }
+
+    fn home_slot(self, hash: usize, capacity: usize) -> usize {
+        if capacity == 0 {
+            return 0;
+        }
+        match self {
+            CapacityPolicy::NextPowerOfTwo => {
+                // Fibonacci hashing
+                let mixed = (hash as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                (mixed ^ (mixed >> 32)) as usize & (capacity - 1)
+            }
+            CapacityPolicy::NextPrime => hash % capacity,
+        }
+    }
+
+    // `pos` is below twice the capacity.
+    fn wrap(self, pos: usize, capacity: usize) -> usize {
+        match self {
+            CapacityPolicy::NextPowerOfTwo => pos & (capacity - 1),
+            CapacityPolicy::NextPrime if pos >= capacity => pos - capacity,
+            CapacityPolicy::NextPrime => pos,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashTableError {
+    CapacityOverflow,
+    AllocError { capacity: usize },
+    CapacityTooSmall { capacity: usize, entries: usize },
+}
+
+impl Display for HashTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashTableError::CapacityOverflow => f.write_str("capacity overflow"),
+            HashTableError::AllocError { capacity } => {
+                write!(f, "memory allocation for {} slots failed", capacity)
+            }
+            HashTableError::CapacityTooSmall { capacity, entries } => {
+                write!(f, "{} slots can't hold {} entries", capacity, entries)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HashTableError {}
+
+pub type TryReserveError = HashTableError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvariantViolation {
+    SlotsUsedMismatch { recorded: usize, actual: usize },
+    DeletedSlotsMismatch { recorded: usize, actual: usize },
+    UnreachableKey { index: usize },
+    StaleHash { index: usize },
+    StaleNeighborBit { bucket: usize, offset: usize },
+    ProbeOrder { index: usize },
+    StashOverflow { len: usize },
+}
+
+impl Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::SlotsUsedMismatch { recorded, actual } => write!(
+                f,
+                "slots_used is {} but {} slots are occupied",
+                recorded, actual
+            ),
+            InvariantViolation::DeletedSlotsMismatch { recorded, actual } => write!(
+                f,
+                "deleted_slots is {} but {} slots are tombstoned",
+                recorded, actual
+            ),
+            InvariantViolation::UnreachableKey { index } => {
+                write!(
+                    f,
+                    "the key in slot {} can't be reached from its home slot",
+                    index
+                )
+            }
+            InvariantViolation::StaleHash { index } => {
+                write!(f, "the cached hash in slot {} doesn't match its key", index)
+            }
+            InvariantViolation::StaleNeighborBit { bucket, offset } => write!(
+                f,
+                "bucket {} marks offset {} but that slot holds none of its keys",
+                bucket, offset
+            ),
+            InvariantViolation::ProbeOrder { index } => write!(
+                f,
+                "the key in slot {} is further from its home than the slot before allows",
+                index
+            ),
+            InvariantViolation::StashOverflow { len } => {
+                write!(
+                    f,
+                    "the stash holds {} entries, more than it has room for",
+                    len
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaceKeyError {
+    MissingKey,
+    KeyExists,
+}
+
+impl Display for ReplaceKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplaceKeyError::MissingKey => f.write_str("key to replace is not in the table"),
+            ReplaceKeyError::KeyExists => f.write_str("replacement key is already in the table"),
+        }
+    }
+}
+
+impl std::error::Error for ReplaceKeyError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct OccupiedError<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+impl<K, V> Display for OccupiedError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("key is already present in the table")
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> std::error::Error for OccupiedError<K, V> {}
+
+pub enum Entry<'a, K: HashableKey, V: HashValue, H: Hasher<K>> {
+    Occupied(OccupiedEntry<'a, K, V, H>),
+    Vacant(VacantEntry<'a, K, V, H>),
+}
+
+pub struct OccupiedEntry<'a, K: HashableKey, V: HashValue, H: Hasher<K>> {
+    table: &'a mut SimpleHashTable<K, V, H>,
+    pos: usize,
+}
+
+pub struct VacantEntry<'a, K: HashableKey, V: HashValue, H: Hasher<K>> {
+    table: &'a mut SimpleHashTable<K, V, H>,
+    key: K,
+    hash: usize,
+    pos: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue, H: Hasher<K>> Entry<'a, K, V, H> {
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+impl<'a, K: HashableKey, V: HashValue, H: Hasher<K>> OccupiedEntry<'a, K, V, H> {
+    fn kv(&self) -> &HashedKVPair<K, V> {
+        self.table.data[self.pos].occupied().unwrap()
+    }
+
+    fn kv_mut(&mut self) -> &mut HashedKVPair<K, V> {
+        self.table.data[self.pos].occupied_mut().unwrap()
+    }
+
+    pub fn key(&self) -> &K {
+        &self.kv().key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.kv().value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.kv_mut().value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.table.data[self.pos].occupied_mut().unwrap().value
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+}
+
+impl<'a, K: HashableKey, V: HashValue, H: Hasher<K>> VacantEntry<'a, K, V, H> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.table.place_at(self.pos, self.hash, self.key, value);
+        &mut self.table.data[self.pos].occupied_mut().unwrap().value
+    }
+}
+
+pub enum EntryRef<'a, 'b, K: HashableKey, Q: ?Sized, V: HashValue, H: Hasher<K>> {
+    Occupied(OccupiedEntry<'a, K, V, H>),
+    Vacant(VacantEntryRef<'a, 'b, K, Q, V, H>),
+}
+
+pub struct VacantEntryRef<'a, 'b, K: HashableKey, Q: ?Sized, V: HashValue, H: Hasher<K>> {
+    table: &'a mut SimpleHashTable<K, V, H>,
+    key: &'b Q,
+    hash: usize,
+    pos: usize,
+}
+
+impl<'a, 'b, K, Q, V, H> EntryRef<'a, 'b, K, Q, V, H>
+where
+    K: HashableKey + From<&'b Q>,
+    Q: ?Sized,
+    V: HashValue,
+    H: Hasher<K>,
+{
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            EntryRef::Occupied(mut entry) => {
+                f(entry.get_mut());
+                EntryRef::Occupied(entry)
+            }
+            EntryRef::Vacant(entry) => EntryRef::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, 'b, K, Q, V, H> VacantEntryRef<'a, 'b, K, Q, V, H>
+where
+    K: HashableKey + From<&'b Q>,
+    Q: ?Sized,
+    V: HashValue,
+    H: Hasher<K>,
+{
+    pub fn key(&self) -> &'b Q {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.table
+            .place_at(self.pos, self.hash, K::from(self.key), value);
+        &mut self.table.data[self.pos].occupied_mut().unwrap().value
+    }
+}
+
+// The hash handed to the raw entry API must be the one `H` computes for the key,
+// otherwise the entry becomes unreachable through the regular lookups.
+pub struct RawEntryBuilder<'a, K: HashableKey, V: HashValue, H: Hasher<K>> {
+    table: &'a SimpleHashTable<K, V, H>,
+}
+
+impl<'a, K: HashableKey, V: HashValue, H: Hasher<K>> RawEntryBuilder<'a, K, V, H> {
+    pub fn from_hash<F: Fn(&K) -> bool>(self, hash: usize, is_match: F) -> Option<(&'a K, &'a V)> {
+        let kv = self.table.lookup(hash, is_match)?;
+        Some((&kv.key, &kv.value))
+    }
+}
+
+pub struct RawEntryBuilderMut<'a, K: HashableKey, V: HashValue, H: Hasher<K>> {
+    table: &'a mut SimpleHashTable<K, V, H>,
+}
+
+impl<'a, K: HashableKey, V: HashValue, H: Hasher<K>> RawEntryBuilderMut<'a, K, V, H> {
+    pub fn from_hash<F: Fn(&K) -> bool>(
+        self,
+        hash: usize,
+        is_match: F,
+    ) -> RawEntryMut<'a, K, V, H> {
+        match self.table.find_slot_mut(hash, is_match) {
+            ProbeResult::Found(pos) => RawEntryMut::Occupied(OccupiedEntry {
+                table: self.table,
+                pos,
+            }),
+            ProbeResult::Vacant(vacant) => RawEntryMut::Vacant(RawVacantEntryMut {
+                table: self.table,
+                hash,
+                vacant,
+            }),
+        }
+    }
+}
+
+pub enum RawEntryMut<'a, K: HashableKey, V: HashValue, H: Hasher<K>> {
+    Occupied(OccupiedEntry<'a, K, V, H>),
+    Vacant(RawVacantEntryMut<'a, K, V, H>),
+}
+
+pub struct RawVacantEntryMut<'a, K: HashableKey, V: HashValue, H: Hasher<K>> {
+    table: &'a mut SimpleHashTable<K, V, H>,
+    hash: usize,
+    vacant: Option<usize>,
+}
+
+impl<'a, K: HashableKey, V: HashValue, H: Hasher<K>> RawVacantEntryMut<'a, K, V, H> {
+    pub fn insert(self, key: K, value: V) -> (&'a K, &'a mut V) {
+        let pos = self.table.claim_slot(self.hash, self.vacant);
+        self.table.place_at(pos, self.hash, key, value);
+        let kv = self.table.data[pos].occupied_mut().unwrap();
+        (&kv.key, &mut kv.value)
+    }
+}
+
+type SlotIter<'a, K, V> = std::slice::Iter<'a, Slot<K, V>>;
+
+pub struct Iter<'a, K: HashableKey, V: HashValue> {
+    // `data`, then whatever is left of the old array
+    slots: std::iter::Chain<SlotIter<'a, K, V>, SlotIter<'a, K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for element in self.slots.by_ref() {
+            if let Slot::Occupied(kv) = element {
+                self.remaining -= 1;
+                return Some((&kv.key, &kv.value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K: HashableKey, V: HashValue> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Iter {
+            slots: self.slots.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotState {
+    Empty,
+    Tombstone,
+    Occupied,
+}
+
+// Heap bytes held by a table. The slot counts are exact: each slot stores the
+// entry, its cached hash and whether it's empty or a tombstone, so there is no
+// other per-slot structure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryUsage {
+    pub slot_bytes: usize,
+    // The old array, while an incremental resize is migrating out of it.
+    pub migrating_bytes: usize,
+    pub owned_bytes: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.slot_bytes + self.migrating_bytes + self.owned_bytes
+    }
+}
+
+pub const PROBE_HISTOGRAM_BUCKETS: usize = 8;
+
+// Distances are counted from each entry's home slot, as placed by the table's
+// CapacityPolicy. The last histogram bucket also counts every longer distance.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ProbeStats {
+    pub entries: usize,
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    pub histogram: [usize; PROBE_HISTOGRAM_BUCKETS],
+    pub longest_run: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotInfo<'a, K> {
+    pub index: usize,
+    pub state: SlotState,
+    pub key: Option<&'a K>,
+    pub home: Option<usize>,
+    pub probe_distance: Option<usize>,
+}
+
+pub struct ProbeIter<'a, K: HashableKey, V: HashValue> {
+    data: &'a [Slot<K, V>],
+    start: usize,
+    step: usize,
+    done: bool,
+    policy: CapacityPolicy,
+}
+
+impl<K: HashableKey, V: HashValue> Iterator for ProbeIter<'_, K, V> {
+    type Item = (usize, SlotState);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let capacity = self.data.len();
+        if self.done || self.step >= capacity {
+            return None;
+        }
+        let pos = self.policy.wrap(self.start + self.step, capacity);
+        self.step += 1;
+        let next = self.policy.wrap(pos + 1, capacity);
+        prefetch::prefetch_read(self.data.as_ptr().wrapping_add(next));
+        let state = self.data[pos].state();
+        self.done = state == SlotState::Empty;
+        Some((pos, state))
+    }
+}
+
+pub struct Slots<'a, K: HashableKey, V: HashValue, H: Hasher<K>> {
+    slots: std::iter::Enumerate<std::slice::Iter<'a, Slot<K, V>>>,
+    capacity: usize,
+    policy: CapacityPolicy,
+    ph_1: PhantomData<H>,
+}
+
+impl<'a, K: HashableKey, V: HashValue, H: Hasher<K>> Iterator for Slots<'a, K, V, H> {
+    type Item = SlotInfo<'a, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, element) = self.slots.next()?;
+        let capacity = self.capacity;
+        let mut info = SlotInfo {
+            index,
+            state: SlotState::Empty,
+            key: None,
+            home: None,
+            probe_distance: None,
+        };
+        info.state = element.state();
+        if let Slot::Occupied(kv) = element {
+            let home = self.policy.home_slot(kv.hash, capacity);
+            info.key = Some(&kv.key);
+            info.home = Some(home);
+            info.probe_distance = Some(self.policy.wrap(index + capacity - home, capacity));
+        }
+        Some(info)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slots.size_hint()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> ExactSizeIterator for Slots<'_, K, V, H> {}
+
+pub struct Keys<'a, K: HashableKey, V: HashValue> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for Keys<'_, K, V> {}
+
+impl<K: HashableKey, V: HashValue> Clone for Keys<'_, K, V> {
+    fn clone(&self) -> Self {
+        Keys {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, K: HashableKey, V: HashValue, H: Hasher<K>> IntoIterator for &'a SimpleHashTable<K, V, H> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Values<'a, K: HashableKey, V: HashValue> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for Values<'_, K, V> {}
+
+impl<K: HashableKey, V: HashValue> Clone for Values<'_, K, V> {
+    fn clone(&self) -> Self {
+        Values {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct IterMut<'a, K: HashableKey, V: HashValue> {
+    slots: std::slice::IterMut<'a, Slot<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for element in self.slots.by_ref() {
+            if let Slot::Occupied(kv) = element {
+                self.remaining -= 1;
+                return Some((&kv.key, &mut kv.value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for IterMut<'_, K, V> {}
+
+pub struct ValuesMut<'a, K: HashableKey, V: HashValue> {
+    inner: IterMut<'a, K, V>,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for ValuesMut<'_, K, V> {}
+
+impl<'a, K: HashableKey, V: HashValue, H: Hasher<K>> IntoIterator
+    for &'a mut SimpleHashTable<K, V, H>
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+pub struct IntoIter<K: HashableKey, V: HashValue> {
+    slots: std::vec::IntoIter<Slot<K, V>>,
+    remaining: usize,
+}
+
+impl<K: HashableKey, V: HashValue> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for element in self.slots.by_ref() {
+            if let Slot::Occupied(kv) = element {
+                self.remaining -= 1;
+                return Some((kv.key, kv.value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> IntoIterator for SimpleHashTable<K, V, H> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.finish_migration();
+        IntoIter {
+            slots: self.data.into_iter(),
+            remaining: self.slots_used,
+        }
+    }
+}
+
+pub struct IntoKeys<K: HashableKey, V: HashValue> {
+    inner: IntoIter<K, V>,
+}
+
+impl<K: HashableKey, V: HashValue> Iterator for IntoKeys<K, V> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for IntoKeys<K, V> {}
+
+pub struct IntoValues<K: HashableKey, V: HashValue> {
+    inner: IntoIter<K, V>,
+}
+
+impl<K: HashableKey, V: HashValue> Iterator for IntoValues<K, V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for IntoValues<K, V> {}
+
+pub struct Drain<'a, K: HashableKey, V: HashValue> {
+    slots: std::slice::IterMut<'a, Slot<K, V>>,
+    remaining: usize,
+}
+
+impl<K: HashableKey, V: HashValue> Iterator for Drain<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for element in self.slots.by_ref() {
+            if let Some(kv) = element.take() {
+                self.remaining -= 1;
+                return Some((kv.key, kv.value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for Drain<'_, K, V> {}
+
+impl<K: HashableKey, V: HashValue> Drop for Drain<'_, K, V> {
+    fn drop(&mut self) {
+        for element in self.slots.by_ref() {
+            *element = Slot::Empty;
+        }
+    }
+}
+
+pub struct ExtractIf<'a, K: HashableKey, V: HashValue, H: Hasher<K>, F: FnMut(&K, &mut V) -> bool> {
+    table: &'a mut SimpleHashTable<K, V, H>,
+    pos: usize,
+    pred: F,
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>, F: FnMut(&K, &mut V) -> bool> Iterator
+    for ExtractIf<'_, K, V, H, F>
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.table.data.len() {
+            let curr = self.pos;
+            self.pos += 1;
+            let selected = match self.table.data[curr].occupied_mut() {
+                Some(kv) => (self.pred)(&kv.key, &mut kv.value),
+                None => continue,
+            };
+            if selected {
+                let kv = self.table.take_at(curr);
+                return Some((kv.key, kv.value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.table.slots_used))
+    }
+}
+
+pub struct CursorMut<'a, K: HashableKey, V: HashValue, H: Hasher<K>> {
+    table: &'a mut SimpleHashTable<K, V, H>,
+    pos: usize,
+    current: Option<usize>,
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> CursorMut<'_, K, V, H> {
+    fn next_occupied(&self) -> Option<usize> {
+        (self.pos..self.table.data.len()).find(|&i| matches!(self.table.data[i], Slot::Occupied(_)))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(&K, &mut V)> {
+        let Some(found) = self.next_occupied() else {
+            self.pos = self.table.data.len();
+            self.current = None;
+            return None;
+        };
+        self.pos = found + 1;
+        self.current = Some(found);
+        let kv = self.table.data[found].occupied_mut().unwrap();
+        Some((&kv.key, &mut kv.value))
+    }
+
+    pub fn peek(&self) -> Option<(&K, &V)> {
+        let kv = self.table.data[self.next_occupied()?].occupied().unwrap();
+        Some((&kv.key, &kv.value))
+    }
+
+    pub fn remove_current(&mut self) -> Option<(K, V)> {
+        let curr = self.current.take()?;
+        let kv = self.table.take_at(curr);
+        Some((kv.key, kv.value))
+    }
+}
+
+impl<K: HashableKey + Clone, V: HashValue + Clone, H: Hasher<K>> Clone
+    for SimpleHashTable<K, V, H>
+{
+    fn clone(&self) -> Self {
+        SimpleHashTable {
+            data: self.data.clone(),
+            old: self.old.clone(),
+            migrated: self.migrated,
+            slots_used: self.slots_used,
+            deleted_slots: self.deleted_slots,
+            resize_count: self.resize_count,
+            generation: self.generation,
+            next_stamp: self.next_stamp,
+            pop_cursor: self.pop_cursor,
+            config: self.config,
+            ph_1: PhantomData,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        if self.data.len() >= source.data.len() {
+            self.data.clear();
+            self.data.extend(source.data.iter().cloned());
+        } else {
+            self.data = source.data.clone();
+        }
+        self.old.clone_from(&source.old);
+        self.migrated = source.migrated;
+        self.slots_used = source.slots_used;
+        self.deleted_slots = source.deleted_slots;
+        self.resize_count = source.resize_count;
+        self.generation = std::cmp::max(self.generation, source.generation) + 1;
+        self.next_stamp = source.next_stamp;
+        self.pop_cursor = source.pop_cursor;
+        self.config = source.config;
+    }
+}
+
+impl<K: HashableKey, V: HashValue + PartialEq, H: Hasher<K>> PartialEq
+    for SimpleHashTable<K, V, H>
+{
+    fn eq(&self, other: &Self) -> bool {
+        if self.size() != other.size() {
+            return false;
+        }
+        self.iter()
+            .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K: HashableKey, V: HashValue + Eq, H: Hasher<K>> Eq for SimpleHashTable<K, V, H> {}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> std::ops::Index<&K> for SimpleHashTable<K, V, H> {
+    type Output = V;
+
+    fn index(&self, key: &K) -> &V {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("key not found in the table"),
+        }
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> std::ops::IndexMut<&K>
+    for SimpleHashTable<K, V, H>
+{
+    fn index_mut(&mut self, key: &K) -> &mut V {
+        match self.get_mut(key) {
+            Some(value) => value,
+            None => panic!("key not found in the table"),
+        }
+    }
+}
+
+struct DebugEntries<'a, K: HashableKey, V: HashValue>(Iter<'a, K, V>);
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug> fmt::Debug for DebugEntries<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.0.clone()).finish()
+    }
+}
+
+enum DebugSlot<'a, K, V> {
+    Empty,
+    Tombstone,
+    Occupied(&'a K, &'a V),
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for DebugSlot<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebugSlot::Empty => f.write_str("Empty"),
+            DebugSlot::Tombstone => f.write_str("Tombstone"),
+            DebugSlot::Occupied(key, value) => {
+                f.debug_tuple("Occupied").field(key).field(value).finish()
+            }
+        }
+    }
+}
+
+struct DebugSlots<'a, K: HashableKey, V: HashValue> {
+    data: &'a [Slot<K, V>],
+}
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug> fmt::Debug for DebugSlots<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slots = self.data.iter().map(|element| match element {
+            Slot::Empty => DebugSlot::Empty,
+            Slot::Tombstone => DebugSlot::Tombstone,
+            Slot::Occupied(kv) => DebugSlot::Occupied(&kv.key, &kv.value),
+        });
+        f.debug_list().entries(slots).finish()
+    }
+}
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug, H: Hasher<K>> fmt::Debug
+    for SimpleHashTable<K, V, H>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let alternate = f.alternate();
+        let mut debug = f.debug_struct("SimpleHashTable");
+        debug
+            .field("size", &self.size())
+            .field("capacity", &self.capacity())
+            .field("wasted_capacity", &self.wasted_capacity())
+            .field("entries", &DebugEntries(self.iter()));
+        if alternate {
+            debug.field("slots", &DebugSlots { data: &self.data });
+        }
+        debug.finish()
+    }
+}
+
+/// Creates an empty table with `DEFAULT_CAPACITY` (16) slots.
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> Default for SimpleHashTable<K, V, H> {
+    fn default() -> Self {
+        create_simple_hash_table(DEFAULT_CAPACITY)
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> Extend<(K, V)> for SimpleHashTable<K, V, H> {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        self.insert_many(iter);
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> FromIterator<(K, V)> for SimpleHashTable<K, V, H> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let iter = iter.into_iter();
+        let mut table = Self::for_entries(std::cmp::max(iter.size_hint().0, DEFAULT_CAPACITY));
+        table.extend(iter);
+        table
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> From<Vec<(K, V)>> for SimpleHashTable<K, V, H> {
+    fn from(pairs: Vec<(K, V)>) -> Self {
+        let mut table = Self::for_entries(std::cmp::max(pairs.len(), DEFAULT_CAPACITY));
+        table.insert_many(pairs);
+        table
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>, S> From<std::collections::HashMap<K, V, S>>
+    for SimpleHashTable<K, V, H>
+{
+    fn from(map: std::collections::HashMap<K, V, S>) -> Self {
+        let mut table = Self::for_entries(std::cmp::max(map.len(), DEFAULT_CAPACITY));
+        table.insert_many(map);
+        table
+    }
+}
+
+impl<K: HashableKey + std::hash::Hash + Eq, V: HashValue, H: Hasher<K>>
+    From<SimpleHashTable<K, V, H>> for std::collections::HashMap<K, V>
+{
+    fn from(table: SimpleHashTable<K, V, H>) -> Self {
+        let mut map = std::collections::HashMap::with_capacity(table.size());
+        map.extend(table);
+        map
+    }
+}
+
+pub fn create_simple_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>>(
+    capacity: usize,
+) -> SimpleHashTable<K, V, H> {
+    create_configured_hash_table(capacity, TableConfig::default())
+}
+
+fn create_configured_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>>(
+    capacity: usize,
+    config: TableConfig,
+) -> SimpleHashTable<K, V, H> {
+    let capacity = config
+        .capacity_policy
+        .round(capacity)
+        .expect("capacity overflow");
+    let mut data = Vec::<Slot<K, V>>::with_capacity(capacity);
+    data.resize_with(capacity, || Slot::Empty);
+
+    SimpleHashTable {
+        data,
+        old: Vec::new(),
+        migrated: 0,
+        slots_used: 0,
+        deleted_slots: 0,
+        resize_count: 0,
+        generation: 0,
+        next_stamp: 0,
+        pop_cursor: 0,
+        config,
+        ph_1: Default::default(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuilderError {
+    ZeroCapacity,
+    InvalidMaxLoadFactor(f64),
+    InvalidGrowthFactor(f64),
+    InvalidTombstoneThreshold(f64),
+    ZeroMigrationBatch,
+    InvalidShrinkThreshold(f64),
+}
+
+impl Display for BuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuilderError::ZeroCapacity => f.write_str("capacity must be greater than zero"),
+            BuilderError::InvalidMaxLoadFactor(lf) => {
+                write!(f, "max load factor {} is not in (0, 1)", lf)
+            }
+            BuilderError::InvalidGrowthFactor(g) => {
+                write!(f, "growth factor {} must be greater than 1", g)
+            }
+            BuilderError::InvalidTombstoneThreshold(t) => {
+                write!(f, "tombstone threshold {} is not in (0, 1]", t)
+            }
+            BuilderError::ZeroMigrationBatch => {
+                f.write_str("migration batch must be greater than zero")
+            }
+            BuilderError::InvalidShrinkThreshold(t) => write!(
+                f,
+                "shrink threshold {} must be positive and below the load right after growing",
+                t
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuilderError {}
+
+pub struct SimpleHashTableBuilder<K: HashableKey, V: HashValue, H: Hasher<K>> {
+    capacity: usize,
+    max_load_factor: Option<f64>,
+    growth_factor: Option<f64>,
+    tombstone_threshold: Option<f64>,
+    capacity_policy: CapacityPolicy,
+    migration_batch: Option<usize>,
+    shrink_threshold: Option<f64>,
+    min_capacity: Option<usize>,
+    ph_1: PhantomData<(K, V, H)>,
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> SimpleHashTableBuilder<K, V, H> {
+    pub fn new() -> Self {
+        SimpleHashTableBuilder {
+            capacity: DEFAULT_CAPACITY,
+            max_load_factor: None,
+            growth_factor: None,
+            tombstone_threshold: None,
+            capacity_policy: CapacityPolicy::default(),
+            migration_batch: None,
+            shrink_threshold: None,
+            min_capacity: None,
+            ph_1: PhantomData,
+        }
+    }
+
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn max_load_factor(mut self, max_load_factor: f64) -> Self {
+        self.max_load_factor = Some(max_load_factor);
+        self
+    }
+
+    pub fn growth_factor(mut self, growth_factor: f64) -> Self {
+        self.growth_factor = Some(growth_factor);
+        self
+    }
+
+    pub fn tombstone_threshold(mut self, tombstone_threshold: f64) -> Self {
+        self.tombstone_threshold = Some(tombstone_threshold);
+        self
+    }
+
+    pub fn capacity_policy(mut self, capacity_policy: CapacityPolicy) -> Self {
+        self.capacity_policy = capacity_policy;
+        self
+    }
+
+    // Spreads each resize over the following operations instead of rehashing
+    // every entry inside the insert that triggers it.
+    pub fn incremental_resize(mut self, enabled: bool) -> Self {
+        self.migration_batch = match enabled {
+            true => Some(self.migration_batch.unwrap_or(DEFAULT_MIGRATION_BATCH)),
+            false => None,
+        };
+        self
+    }
+
+    // Enables incremental resizing, migrating `batch` old slots per operation.
+    pub fn migration_batch(mut self, batch: usize) -> Self {
+        self.migration_batch = Some(batch);
+        self
+    }
+
+    // Rebuilds into a smaller array once fewer than `threshold * capacity`
+    // entries are left, down to `min_capacity` slots.
+    pub fn shrink_threshold(mut self, threshold: f64) -> Self {
+        self.shrink_threshold = Some(threshold);
+        self
+    }
+
+    // Defaults to the builder's capacity.
+    pub fn min_capacity(mut self, min_capacity: usize) -> Self {
+        self.min_capacity = Some(min_capacity);
+        self
+    }
+
+    pub fn build(self) -> Result<SimpleHashTable<K, V, H>, BuilderError> {
+        if self.capacity == 0 {
+            return Err(BuilderError::ZeroCapacity);
+        }
+        let max_load_factor = match self.max_load_factor {
+            Some(lf) if !valid_max_load_factor(lf) => {
+                return Err(BuilderError::InvalidMaxLoadFactor(lf))
+            }
+            Some(lf) => lf,
+            None => DEFAULT_MAX_LOAD_FACTOR,
+        };
+        let growth_factor = match self.growth_factor {
+            Some(g) if !(g > 1.0 && g.is_finite()) => {
+                return Err(BuilderError::InvalidGrowthFactor(g))
+            }
+            Some(g) => g,
+            None => DEFAULT_GROWTH_FACTOR,
+        };
+        let tombstone_threshold = match self.tombstone_threshold {
+            Some(t) if !valid_tombstone_threshold(t) => {
+                return Err(BuilderError::InvalidTombstoneThreshold(t))
+            }
+            Some(t) => t,
+            None => DEFAULT_TOMBSTONE_THRESHOLD,
+        };
+        if self.migration_batch == Some(0) {
+            return Err(BuilderError::ZeroMigrationBatch);
+        }
+        // Right after growing the load is max_load_factor / growth_factor, and
+        // right after shrinking it is at most half of max_load_factor.
+        let lowest_settled_load = max_load_factor / growth_factor.max(2.0);
+        if let Some(t) = self.shrink_threshold {
+            if !(t > 0.0 && t < lowest_settled_load) {
+                return Err(BuilderError::InvalidShrinkThreshold(t));
+            }
+        }
+        Ok(create_configured_hash_table(
+            self.capacity,
+            TableConfig {
+                max_load_factor,
+                growth_factor,
+                tombstone_threshold,
+                capacity_policy: self.capacity_policy,
+                migration_batch: self.migration_batch,
+                shrink_threshold: self.shrink_threshold,
+                min_capacity: self.min_capacity.unwrap_or(self.capacity),
+            },
+        ))
+    }
+}
+
+fn valid_max_load_factor(max_load_factor: f64) -> bool {
+    max_load_factor > 0.0 && max_load_factor < 1.0
+}
+
+fn valid_tombstone_threshold(threshold: f64) -> bool {
+    threshold > 0.0 && threshold <= 1.0
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> Default for SimpleHashTableBuilder<K, V, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a `SimpleHashTable` from `key => value` pairs, optionally followed by
+/// `; HasherType`. Without a hasher the type is left to inference.
+///
+/// ```
+/// use rust_ht::{hashtable, HashTable, SimpleHashTable, SimpleHasher};
+///
+/// let t = hashtable! {1 => 10, 2 => 20; SimpleHasher};
+/// assert_eq!(t.size(), 2);
+/// assert_eq!(t.get(&2), Some(&20));
+///
+/// let empty: SimpleHashTable<i64, i64, SimpleHasher> = hashtable! {};
+/// assert!(empty.is_empty());
+/// ```
+#[macro_export]
+macro_rules! hashtable {
+    (@count) => { 0usize };
+    (@count $head:expr $(, $tail:expr)*) => { 1usize + $crate::hashtable!(@count $($tail),*) };
+    ($($key:expr => $value:expr),* $(,)? ; $hasher:ty) => {{
+        #[allow(unused_mut)]
+        let mut table =
+            $crate::SimpleHashTable::<_, _, $hasher>::for_entries($crate::hashtable!(@count $($key),*));
+        $(
+            $crate::HashTable::insert(&mut table, $key, $value);
+        )*
+        table
+    }};
+    ($($key:expr => $value:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut table = $crate::SimpleHashTable::for_entries($crate::hashtable!(@count $($key),*));
+        $(
+            $crate::HashTable::insert(&mut table, $key, $value);
+        )*
+        table
+    }};
+}
+
+// splitmix64 finalizer over the two's-complement bits, folded to 32 bits so the
+// result is the same on every target width.
+pub struct SimpleHasher;
+impl Hasher<i64> for SimpleHasher {
+    fn hash(key: &i64) -> usize {
+        let mut x = *key as u64;
+        x ^= x >> 30;
+        x = x.wrapping_mul(0xbf58476d1ce4e5b9);
+        x ^= x >> 27;
+        x = x.wrapping_mul(0x94d049bb133111eb);
+        x ^= x >> 31;
+        ((x ^ (x >> 32)) as u32) as usize
+    }
+}
+
+// The previous SimpleHasher: the key itself, reinterpreted as usize.
+pub struct IdentityHasher;
+impl Hasher<i64> for IdentityHasher {
+    fn hash(key: &i64) -> usize {
+        *key as usize
+    }
+}
+
+impl HashableKey for i64 {}
+impl HashableKey for String {}
+impl HashableKey for Vec<u8> {}
+
+/// An `f64` key with total equality: every NaN equals every other NaN, and
+/// `-0.0` equals `0.0`. Plain floats can't be keys because `NaN != NaN` would
+/// let the same key be inserted twice and never be found again.
+#[derive(Debug, Clone, Copy)]
+pub struct OrderedFloatKey(pub f64);
+
+impl OrderedFloatKey {
+    fn canonical_bits(&self) -> u64 {
+        if self.0.is_nan() {
+            f64::NAN.to_bits()
+        } else if self.0 == 0.0 {
+            0
+        } else {
+            self.0.to_bits()
+        }
+    }
+}
+
+impl PartialEq for OrderedFloatKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical_bits() == other.canonical_bits()
+    }
+}
+
+impl Eq for OrderedFloatKey {}
+
+impl HashableKey for OrderedFloatKey {}
+
+impl From<f64> for OrderedFloatKey {
+    fn from(value: f64) -> Self {
+        OrderedFloatKey(value)
+    }
+}
+
+impl Hasher<OrderedFloatKey> for SimpleHasher {
+    fn hash(key: &OrderedFloatKey) -> usize {
+        <SimpleHasher as Hasher<i64>>::hash(&(key.canonical_bits() as i64))
+    }
+}
+
+pub type IntegerToIntegerHT = SimpleHashTable<i64, i64, SimpleHasher>;
+pub fn create_integer_to_integer_ht() -> IntegerToIntegerHT {
+    create_simple_hash_table(32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::collections::{HashMap, HashSet};
+
+    const POLICIES: [CapacityPolicy; 2] =
+        [CapacityPolicy::NextPowerOfTwo, CapacityPolicy::NextPrime];
+
+    // The table `create_integer_to_integer_ht` makes, under each capacity policy.
+    fn policy_table(policy: CapacityPolicy) -> IntegerToIntegerHT {
+        SimpleHashTable::builder()
+            .capacity(32)
+            .capacity_policy(policy)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn it_works() {
+        for policy in POLICIES {
+            let mut ht: SimpleHashTable<i64, i64, SimpleHasher> = policy_table(policy);
+            assert_eq!(ht.size(), 0);
+            ht.insert(25, 32);
+            assert_eq!(ht.size(), 1);
+            ht.insert(25, 32);
+            assert_eq!(ht.size(), 1);
+            ht.insert(25, 32);
+            assert_eq!(ht.size(), 1);
+            ht.insert(25, 32);
+            assert_eq!(ht.size(), 1);
+            ht.insert(25, 32);
+            assert_eq!(ht.size(), 1);
+            let expected = Some(&32i64);
+            assert!(ht.has(&25));
+            assert_eq!(ht.size(), 1);
+            assert_eq!(ht.get(&25), expected);
+            assert_eq!(ht.size(), 1);
+            assert_eq!(ht.get(&25), expected);
+            ht.insert(26, 32);
+            assert_eq!(ht.size(), 2);
+            ht.insert(26, 32);
+            assert_eq!(ht.size(), 2);
+            ht.insert(26, 32);
+            assert_eq!(ht.size(), 2);
+            ht.insert(26, 32);
+            assert_eq!(ht.size(), 2);
+            ht.insert(26, 33);
+            assert_eq!(ht.size(), 2);
+            assert_eq!(*ht.get(&26).unwrap(), 33);
+            assert_valid(&ht);
+            ht.delete(&26);
+            assert_eq!(ht.size(), 1);
+            assert!(!ht.has(&26));
+            assert_valid(&ht);
+        }
+    }
+
+    #[test]
+    fn overflow_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let sz_check: i64 = 1000000;
+            for i in 0..sz_check {
+                ht.insert(i, i);
+                assert_eq!(ht.size(), (i + 1) as usize);
+                if i % 100_000 == 0 {
+                    assert_valid(&ht);
+                }
+            }
+            assert_eq!(ht.size(), sz_check as usize);
+            assert_valid(&ht);
+        }
+    }
+
+    #[test]
+    fn overflow_delete_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let sz_check: i64 = 1000000;
+            for i in 0..sz_check {
+                ht.insert(i, i);
+                ht.delete(&i);
+                assert_eq!(ht.size(), 0);
+                if i % 100_000 == 0 {
+                    assert_valid(&ht);
+                }
+            }
+        }
+    }
+
+    // `validate` checks the table's internal invariants, for tables that have any.
+    fn random_conformance<T: HashTable<i64, i64>>(mut ht: T, validate: impl Fn(&T)) {
+        let sz_check: i64 = 1000;
+        let mut hset: HashSet<i64> = HashSet::new();
+
+        for i in 0..sz_check {
+            let mut num: i64;
+            loop {
+                num = rand::thread_rng().gen_range(-1_000_000_000_000..1_000_000_000_000);
+                if hset.insert(num) {
+                    break;
+                }
+            }
+            ht.insert(num, num);
+            for item in hset.iter() {
+                assert!(ht.has(item));
+            }
+            assert_eq!(ht.size(), (i + 1) as usize);
+            if i % 50 == 0 {
+                validate(&ht);
+            }
+        }
+        validate(&ht);
+        for (i, item) in hset.iter().step_by(2).enumerate() {
+            assert_eq!(ht.remove(item), Some(*item));
+            if i % 50 == 0 {
+                validate(&ht);
+            }
+        }
+        validate(&ht);
+        assert_eq!(ht.size(), hset.len() - hset.len().div_ceil(2));
+        for (i, item) in hset.iter().enumerate() {
+            assert_eq!(ht.get(item).is_some(), i % 2 == 1);
+        }
+        ht.clear();
+        assert!(ht.is_empty());
+        validate(&ht);
+    }
+
+    #[test]
+    fn overflow_random_test() {
+        for policy in POLICIES {
+            random_conformance(policy_table(policy), assert_valid);
+        }
+        random_conformance(StdHashMapAdapter::<i64, i64>::new(), |_| {});
+        random_conformance(
+            create_no_tombstones_hash_table::<i64, i64, SimpleHasher>(32),
+            |_| {},
+        );
+        random_conformance(
+            SimpleHashTable::<i64, i64, IdentityHasher>::builder()
+                .capacity_policy(CapacityPolicy::NextPrime)
+                .build()
+                .unwrap(),
+            assert_valid,
+        );
+    }
+
+    #[test]
+    fn get_ref_after_delete_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            for i in 0..20 {
+                ht.insert(i, i * 10);
+            }
+            for i in (0..20).step_by(2) {
+                ht.delete(&i);
+            }
+            assert_valid(&ht);
+            for i in 0..20 {
+                if i % 2 == 0 {
+                    assert_eq!(ht.get(&i), None);
+                } else {
+                    assert_eq!(ht.get(&i), Some(&(i * 10)));
+                }
+            }
+            assert_eq!(ht.get_cloned(&5), Some(50));
+            assert_eq!(ht.get_cloned(&4), None);
+        }
+    }
+
+    #[test]
+    fn get_ref_after_resize_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let initial_capacity = ht.capacity();
+            for i in 0..100 {
+                ht.insert(i, i + 1000);
+            }
+            assert!(ht.capacity() > initial_capacity);
+            for i in 0..100 {
+                assert_eq!(ht.get(&i), Some(&(i + 1000)));
+            }
+        }
+    }
+
+    #[test]
+    fn get_mut_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            ht.insert(7, 70);
+            *ht.get_mut(&7).unwrap() += 1;
+            assert_eq!(ht.get(&7), Some(&71));
+            assert_eq!(ht.get_mut(&8), None);
+            assert_eq!(ht.size(), 1);
+        }
+    }
+
+    // The home slot every entry shares, if they do.
+    fn shared_home<K: HashableKey, V: HashValue, H: Hasher<K>>(
+        ht: &SimpleHashTable<K, V, H>,
+    ) -> Option<usize> {
+        let homes: HashSet<usize> = ht.slots().filter_map(|slot| slot.home).collect();
+        match homes.len() {
+            1 => homes.into_iter().next(),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn get_mut_colliding_keys_test() {
+        for policy in POLICIES {
+            let mut ht = SimpleHashTable::<i64, i64, ConstantHasher>::builder()
+                .capacity(32)
+                .capacity_policy(policy)
+                .build()
+                .unwrap();
+            let capacity = ht.capacity() as i64;
+            let keys: Vec<i64> = (0..10).map(|i| i * capacity).collect();
+            for key in keys.iter() {
+                ht.insert(*key, *key);
+            }
+            assert_eq!(shared_home(&ht), Some(0));
+            for key in keys.iter() {
+                let value = ht.get_mut(key).unwrap();
+                *value = -*value - 1;
+            }
+            for key in keys.iter() {
+                assert_eq!(ht.get(key), Some(&(-key - 1)));
+            }
+        }
+    }
+
+    #[test]
+    fn insert_returns_previous_value_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            assert_eq!(ht.insert(3, 30), None);
+            assert_eq!(ht.insert(3, 31), Some(30));
+            assert_eq!(ht.size(), 1);
+            ht.delete(&3);
+            assert_eq!(ht.insert(3, 32), None);
+            assert_eq!(ht.insert(3, 33), Some(32));
+            assert_eq!(ht.get(&3), Some(&33));
+            assert_eq!(ht.size(), 1);
+        }
+    }
+
+    #[test]
+    fn insert_overwrite_after_resize_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let initial_capacity = ht.capacity() as i64;
+            for i in 0..=initial_capacity {
+                assert_eq!(ht.insert(i, i), None);
+            }
+            assert!(ht.capacity() as i64 > initial_capacity);
+            for i in 0..=initial_capacity {
+                assert_eq!(ht.insert(i, i * 2), Some(i));
+            }
+            assert_eq!(ht.size() as i64, initial_capacity + 1);
+            assert_valid(&ht);
+        }
+    }
+
+    #[test]
+    fn remove_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            assert_eq!(ht.remove(&1), None);
+            ht.insert(1, 10);
+            ht.insert(2, 20);
+            assert_eq!(ht.remove(&1), Some(10));
+            assert_eq!(ht.size(), 1);
+            assert_eq!(ht.wasted_capacity(), 1);
+            assert_eq!(ht.remove(&1), None);
+            assert_eq!(ht.size(), 1);
+            assert_eq!(ht.wasted_capacity(), 1);
+            assert!(!ht.has(&1));
+            assert_eq!(ht.get(&2), Some(&20));
+            assert_valid(&ht);
+        }
+    }
+
+    #[test]
+    fn entry_counter_test() {
+        let mut ht = create_integer_to_integer_ht();
+        let mut model: HashMap<i64, i64> = HashMap::new();
+        let mut rng = rand::thread_rng();
+        for _ in 0..10000 {
+            let key = rng.gen_range(0..500);
+            *ht.entry(key).or_insert(0) += 1;
+            *model.entry(key).or_insert(0) += 1;
+        }
+        assert_valid(&ht);
+        assert_eq!(ht.size(), model.len());
+        for (key, count) in model.iter() {
+            assert_eq!(ht.get(key), Some(count));
+        }
+    }
+
+    #[test]
+    fn entry_or_insert_with_and_modify_test() {
+        let mut ht = create_integer_to_integer_ht();
+        let mut calls = 0;
+        for _ in 0..3 {
+            ht.entry(1).and_modify(|v| *v *= 2).or_insert_with(|| {
+                calls += 1;
+                5
+            });
+        }
+        assert_eq!(calls, 1);
+        assert_eq!(ht.get(&1), Some(&20));
+        assert_eq!(*ht.entry(1).key(), 1);
+    }
+
+    #[test]
+    fn entry_vacant_with_resize_test() {
+        let mut ht = create_integer_to_integer_ht();
+        let initial_capacity = ht.capacity() as i64;
+        let fill = (initial_capacity as f64 * ht.max_load_factor()) as i64;
+        for i in 0..fill {
+            ht.insert(i, i);
+        }
+        assert_eq!(ht.capacity() as i64, initial_capacity);
+        *ht.entry(fill).or_insert(0) += 7;
+        assert!(ht.capacity() as i64 > initial_capacity);
+        assert_eq!(ht.get(&fill), Some(&7));
+        for i in 0..fill {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn clear_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let initial_capacity = ht.capacity();
+            for i in 0..20 {
+                ht.insert(i, i);
+            }
+            ht.delete(&3);
+            ht.clear();
+            assert_eq!(ht.size(), 0);
+            assert_eq!(ht.wasted_capacity(), 0);
+            assert_eq!(ht.capacity(), initial_capacity);
+            assert_valid(&ht);
+            for i in 0..20 {
+                assert!(!ht.has(&i));
+            }
+            let fill = (initial_capacity as f64 * ht.max_load_factor()) as i64;
+            for i in 0..fill {
+                ht.insert(i, i + 1);
+            }
+            assert_eq!(ht.capacity(), initial_capacity);
+            for i in 0..fill {
+                assert_eq!(ht.get(&i), Some(&(i + 1)));
+            }
+        }
+    }
+
+    #[test]
+    fn clear_empty_test() {
+        let mut ht = create_integer_to_integer_ht();
+        ht.clear();
+        assert_eq!(ht.size(), 0);
+        assert_eq!(ht.capacity(), 32);
+        ht.insert(1, 1);
+        assert_eq!(ht.get(&1), Some(&1));
+    }
+
+    #[test]
+    fn is_empty_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            assert!(ht.is_empty());
+            for i in 0..100 {
+                ht.insert(i, i);
+                assert!(!ht.is_empty());
+            }
+            for i in 0..100 {
+                ht.delete(&i);
+            }
+            assert!(ht.is_empty());
+            assert_eq!(ht.wasted_capacity(), 100);
+            assert_valid(&ht);
+            ht.insert(1, 1);
+            assert!(!ht.is_empty());
+            ht.clear();
+            assert!(ht.is_empty());
+        }
+    }
+
+    #[test]
+    fn iter_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let mut expected: HashSet<i64> = HashSet::new();
+            for i in 0..1000 {
+                ht.insert(i, i * 3);
+                expected.insert(i);
+            }
+            for i in (0..1000).step_by(3) {
+                ht.delete(&i);
+                expected.remove(&i);
+            }
+            assert_eq!(ht.iter().len(), expected.len());
+            let keys: HashSet<i64> = ht.iter().map(|(k, _)| *k).collect();
+            assert_eq!(keys, expected);
+            for (k, v) in &ht {
+                assert_eq!(*v, *k * 3);
+            }
+        }
+    }
+
+    #[test]
+    fn iter_empty_test() {
+        let ht = create_integer_to_integer_ht();
+        assert_eq!(ht.iter().next(), None);
+        assert_eq!(ht.iter().size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn iter_mut_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            for i in 0..500 {
+                ht.insert(i, i);
+            }
+            for i in (0..500).step_by(5) {
+                ht.delete(&i);
+            }
+            for (_, v) in ht.iter_mut() {
+                *v *= 2;
+            }
+            for i in 0..500 {
+                if i % 5 == 0 {
+                    assert!(!ht.has(&i));
+                } else {
+                    assert_eq!(ht.get(&i), Some(&(i * 2)));
+                }
+            }
+            for (k, v) in &mut ht {
+                *v += *k;
+            }
+            assert_eq!(ht.get(&7), Some(&21));
+        }
+    }
+
+    #[test]
+    fn into_iter_round_trip_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let mut expected: Vec<(i64, i64)> = Vec::new();
+            for i in 0..300 {
+                ht.insert(i, i % 7);
+            }
+            for i in 0..300 {
+                if i % 4 == 0 {
+                    ht.delete(&i);
+                } else {
+                    expected.push((i, i % 7));
+                }
+            }
+            let mut pairs = ht.into_iter().collect::<Vec<_>>();
+            pairs.sort();
+            assert_eq!(pairs, expected);
+        }
+    }
+
+    #[test]
+    fn keys_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            for i in 0..200 {
+                ht.insert(i, -i);
+            }
+            let to_delete: Vec<i64> = ht.keys().filter(|k| *k % 2 == 1).cloned().collect();
+            for key in to_delete.iter() {
+                ht.delete(key);
+            }
+            assert_eq!(ht.keys().count(), ht.size());
+            let keys = ht.keys();
+            assert_eq!(keys.clone().count(), 100);
+            for key in keys {
+                assert_eq!(key % 2, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn values_sum_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let sz_check: i64 = 1000000;
+            for i in 0..sz_check {
+                ht.insert(i, i);
+            }
+            let values = ht.values();
+            assert_eq!(
+                values.size_hint(),
+                (sz_check as usize, Some(sz_check as usize))
+            );
+            let sum: i64 = values.sum();
+            assert_eq!(sum, sz_check * (sz_check - 1) / 2);
+        }
+    }
+
+    #[test]
+    fn values_mut_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let capacity = ht.capacity() as i64;
+            let keys = [
+                capacity - 2,
+                capacity - 1,
+                2 * capacity - 2,
+                2 * capacity - 1,
+                5,
+            ];
+            for key in keys.iter() {
+                ht.insert(*key, 100);
+            }
+            assert_eq!(ht.capacity() as i64, capacity);
+            for v in ht.values_mut() {
+                *v /= 2;
+            }
+            for key in keys.iter() {
+                assert_eq!(ht.get(key), Some(&50));
+            }
+        }
+    }
+
+    fn drain_fixture(policy: CapacityPolicy) -> IntegerToIntegerHT {
+        let mut ht = policy_table(policy);
+        for i in 0..100 {
+            ht.insert(i, i + 1);
+        }
+        for i in (0..100).step_by(10) {
+            ht.delete(&i);
+        }
+        ht
+    }
+
+    #[test]
+    fn drain_full_test() {
+        for policy in POLICIES {
+            let mut ht = drain_fixture(policy);
+            let capacity = ht.capacity();
+            let mut drained: Vec<(i64, i64)> = ht.drain().collect();
+            drained.sort();
+            let expected: Vec<(i64, i64)> = (0..100)
+                .filter(|i| i % 10 != 0)
+                .map(|i| (i, i + 1))
+                .collect();
+            assert_eq!(drained, expected);
+            assert_eq!(ht.size(), 0);
+            assert_eq!(ht.wasted_capacity(), 0);
+            assert_eq!(ht.capacity(), capacity);
+            assert_eq!(ht.iter().count(), 0);
+        }
+    }
+
+    #[test]
+    fn drain_partial_test() {
+        for policy in POLICIES {
+            let mut ht = drain_fixture(policy);
+            let mut drain = ht.drain();
+            assert_eq!(drain.len(), 90);
+            let taken: Vec<(i64, i64)> = drain.by_ref().take(5).collect();
+            assert_eq!(taken.len(), 5);
+            assert_eq!(drain.len(), 85);
+            drop(drain);
+            assert!(ht.is_empty());
+            assert_eq!(ht.wasted_capacity(), 0);
+            assert_eq!(ht.iter().count(), 0);
+            ht.insert(1, 1);
+            assert_eq!(ht.get(&1), Some(&1));
+            assert!(!ht.has(&2));
+        }
+    }
+
+    #[test]
+    fn drain_dropped_immediately_test() {
+        for policy in POLICIES {
+            let mut ht = drain_fixture(policy);
+            ht.drain();
+            assert!(ht.is_empty());
+            assert_eq!(ht.wasted_capacity(), 0);
+            for i in 0..100 {
+                assert!(!ht.has(&i));
+            }
+        }
+    }
+
+    #[test]
+    fn retain_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let mut model: HashMap<i64, i64> = HashMap::new();
+            let mut rng = rand::thread_rng();
+            for i in 0..2000 {
+                let value = rng.gen_range(0..10);
+                ht.insert(i, value);
+                model.insert(i, value);
+            }
+            for _ in 0..300 {
+                let key = rng.gen_range(0..2000);
+                ht.delete(&key);
+                model.remove(&key);
+            }
+            assert_valid(&ht);
+            let threshold = rng.gen_range(0..10);
+            ht.retain(|_, v| {
+                *v += 1;
+                *v > threshold
+            });
+            model.retain(|_, v| {
+                *v += 1;
+                *v > threshold
+            });
+            assert_eq!(ht.size(), model.len());
+            assert_valid(&ht);
+            for i in 0..2000 {
+                assert_eq!(ht.get(&i), model.get(&i));
+            }
+        }
+    }
+
+    #[test]
+    fn extend_test() {
+        let mut ht = create_integer_to_integer_ht();
+        let pairs: Vec<(i64, i64)> = (0..100000).map(|i| (i, i * 2)).collect();
+        ht.extend(pairs);
+        assert_eq!(ht.resize_count(), 1);
+        // one reservation, rounded up to a power of two
+        assert_eq!(ht.capacity(), (100000 * 4 / 3 + 1usize).next_power_of_two());
+        assert_eq!(ht.size(), 100000);
+        for i in 0..100000 {
+            assert_eq!(ht.get(&i), Some(&(i * 2)));
+        }
+    }
+
+    #[test]
+    fn extend_duplicates_test() {
+        let mut ht = create_integer_to_integer_ht();
+        ht.insert(1, 0);
+        ht.extend(vec![(1, 1), (2, 2), (1, 3), (2, 4), (3, 5)]);
+        assert_eq!(ht.size(), 3);
+        assert_eq!(ht.get(&1), Some(&3));
+        assert_eq!(ht.get(&2), Some(&4));
+        assert_eq!(ht.get(&3), Some(&5));
+    }
+
+    #[test]
+    fn from_iter_test() {
+        let ht: SimpleHashTable<i64, i64, SimpleHasher> = (0..5000)
+            .map(|i| (i, i * i))
+            .filter(|(k, _)| k % 3 != 0)
+            .collect();
+        assert_eq!(ht.size(), 3333);
+        for i in 0..5000 {
+            if i % 3 == 0 {
+                assert!(!ht.has(&i));
+            } else {
+                assert_eq!(ht.get(&i), Some(&(i * i)));
+            }
+        }
+        let empty: IntegerToIntegerHT = std::iter::empty().collect();
+        assert!(empty.is_empty());
+        assert!(empty.capacity() >= 16);
+    }
+
+    #[test]
+    fn default_test() {
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::default();
+        assert_eq!(ht.capacity(), 16);
+        assert!(ht.is_empty());
+        for i in 0..100 {
+            ht.insert(i, i);
+        }
+        assert!(ht.capacity() > 16);
+        for i in 0..100 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn clone_test() {
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..200 {
+            ht.insert(i, i);
+        }
+        for i in (0..200).step_by(4) {
+            ht.delete(&i);
+        }
+        let mut snapshot = ht.clone();
+        assert_eq!(snapshot.size(), ht.size());
+        assert_eq!(snapshot.capacity(), ht.capacity());
+        assert_eq!(snapshot.wasted_capacity(), ht.wasted_capacity());
+        for i in 0..200 {
+            snapshot.insert(i, -i);
+        }
+        snapshot.delete(&1);
+        for i in 0..200 {
+            if i % 4 == 0 {
+                assert!(!ht.has(&i));
+            } else {
+                assert_eq!(ht.get(&i), Some(&i));
+            }
+            if i == 1 {
+                assert!(!snapshot.has(&i));
+            } else {
+                assert_eq!(snapshot.get(&i), Some(&-i));
+            }
+        }
+        assert_eq!(ht.size(), 150);
+        assert_eq!(snapshot.size(), 199);
+    }
+
+    #[test]
+    fn debug_test() {
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(4);
+        ht.insert(1, 10);
+        ht.insert(2, 20);
+        ht.insert(3, 30);
+        ht.delete(&3);
+        assert_eq!(
+            format!("{:?}", ht),
+            "SimpleHashTable { size: 2, capacity: 4, wasted_capacity: 1, entries: {2: 20, 1: 10} }"
+        );
+        let pretty = format!("{:#?}", ht);
+        assert!(pretty.contains("slots: ["));
+        assert!(pretty.contains("Empty"));
+        assert!(pretty.contains("Tombstone"));
+        assert!(pretty.contains("Occupied("));
+    }
+
+    #[test]
+    fn eq_test() {
+        let mut a = create_integer_to_integer_ht();
+        for i in 0..100 {
+            a.insert(i, i * 2);
+        }
+        let mut b: IntegerToIntegerHT = create_simple_hash_table(4096);
+        for i in (0..100).rev() {
+            b.insert(i, 0);
+        }
+        for i in 200..300 {
+            b.insert(i, i);
+        }
+        assert!(a != b);
+        for i in 200..300 {
+            b.delete(&i);
+        }
+        assert!(a != b);
+        for i in 0..100 {
+            b.insert(i, i * 2);
+        }
+        assert_ne!(a.capacity(), b.capacity());
+        assert_ne!(a.wasted_capacity(), b.wasted_capacity());
+        assert!(a == b);
+        assert!(b == a);
+        b.delete(&50);
+        assert!(a != b);
+        assert!(create_integer_to_integer_ht() == IntegerToIntegerHT::default());
+    }
+
+    #[test]
+    fn reserve_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let sz_check: i64 = 1000000;
+            ht.reserve(sz_check as usize);
+            let capacity = ht.capacity();
+            assert!(capacity >= sz_check as usize);
+            for i in 0..sz_check {
+                ht.insert(i, i);
+                assert_eq!(ht.capacity(), capacity);
+            }
+            ht.reserve(0);
+            assert_eq!(ht.capacity(), capacity);
+            for i in 0..sz_check {
+                assert_eq!(ht.get(&i), Some(&i));
+            }
+        }
+    }
+
+    #[test]
+    fn reserve_noop_test() {
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..10 {
+            ht.insert(i, i);
+        }
+        ht.reserve(5);
+        assert_eq!(ht.capacity(), 32);
+        ht.reserve(100);
+        assert!(ht.capacity() >= 110);
+        for i in 0..10 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn shrink_to_fit_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            for i in 0..100000 {
+                ht.insert(i, i);
+            }
+            for i in 1000..100000 {
+                ht.delete(&i);
+            }
+            let capacity = ht.capacity();
+            assert_eq!(ht.wasted_capacity(), 99000);
+            ht.shrink_to_fit();
+            assert_eq!(ht.wasted_capacity(), 0);
+            assert_eq!(ht.size(), 1000);
+            assert_eq!(Some(ht.capacity()), policy.round(ht.capacity_for(1000)));
+            if policy == CapacityPolicy::NextPowerOfTwo {
+                assert!(ht.capacity() * 100 <= capacity);
+            }
+            for i in 0..1000 {
+                assert_eq!(ht.get(&i), Some(&i));
+            }
+            for i in (1000..100000).step_by(997) {
+                assert!(!ht.has(&i));
+            }
+            ht.insert(100000, 1);
+            assert_eq!(ht.get(&100000), Some(&1));
+        }
+    }
+
+    #[test]
+    fn shrink_to_fit_empty_test() {
+        let mut ht = create_integer_to_integer_ht();
+        ht.insert(1, 1);
+        ht.delete(&1);
+        ht.shrink_to_fit();
+        assert_eq!(ht.capacity(), 1);
+        assert!(ht.is_empty());
+        ht.insert(2, 2);
+        ht.insert(3, 3);
+        assert_eq!(ht.get(&2), Some(&2));
+        assert_eq!(ht.get(&3), Some(&3));
+    }
+
+    #[test]
+    fn try_insert_test() {
+        let mut ht = create_integer_to_integer_ht();
+        assert_eq!(ht.try_insert(1, 10).map(|v| *v), Ok(10));
+        let err = ht.try_insert(1, 11).unwrap_err();
+        assert_eq!(err, OccupiedError { key: 1, value: 11 });
+        assert_eq!(err.to_string(), "key is already present in the table");
+        assert_eq!(ht.get(&1), Some(&10));
+        assert_eq!(ht.size(), 1);
+        ht.delete(&1);
+        *ht.try_insert(1, 12).unwrap() += 1;
+        assert_eq!(ht.get(&1), Some(&13));
+        assert_eq!(ht.size(), 1);
+    }
+
+    #[test]
+    fn get_or_insert_with_test() {
+        let mut ht = create_integer_to_integer_ht();
+        let mut calls = 0;
+        *ht.get_or_insert_with(4, || {
+            calls += 1;
+            40
+        }) += 1;
+        assert_eq!(calls, 1);
+        *ht.get_or_insert_with(4, || {
+            calls += 1;
+            0
+        }) += 1;
+        assert_eq!(calls, 1);
+        assert_eq!(ht.get(&4), Some(&42));
+    }
+
+    #[test]
+    fn get_or_insert_with_resize_test() {
+        let mut ht = create_integer_to_integer_ht();
+        let capacity = ht.capacity() as i64;
+        for i in 0..capacity {
+            ht.insert(i, i);
+        }
+        let value = ht.get_or_insert_with(capacity, || 1);
+        *value += 1;
+        assert!(ht.capacity() as i64 > capacity);
+        assert_eq!(ht.get(&capacity), Some(&2));
+        assert_eq!(ht.size() as i64, capacity + 1);
+    }
+
+    #[derive(Debug)]
+    struct TaggedKey {
+        id: i64,
+        tag: &'static str,
+    }
+
+    impl PartialEq for TaggedKey {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    impl Eq for TaggedKey {}
+
+    impl HashableKey for TaggedKey {}
+
+    struct TaggedKeyHasher;
+    impl Hasher<TaggedKey> for TaggedKeyHasher {
+        fn hash(key: &TaggedKey) -> usize {
+            key.id as usize
+        }
+    }
+
+    #[test]
+    fn get_key_value_test() {
+        let mut ht: SimpleHashTable<TaggedKey, i64, TaggedKeyHasher> = create_simple_hash_table(8);
+        ht.insert(
+            TaggedKey {
+                id: 1,
+                tag: "original",
+            },
+            10,
+        );
+        ht.insert(
+            TaggedKey {
+                id: 1,
+                tag: "overwrite",
+            },
+            11,
+        );
+        let lookup = TaggedKey {
+            id: 1,
+            tag: "lookup",
+        };
+        let (key, value) = ht.get_key_value(&lookup).unwrap();
+        assert_eq!(key.tag, "original");
+        assert_eq!(*value, 11);
+        assert!(ht.get_key_value(&TaggedKey { id: 2, tag: "" }).is_none());
+    }
+
+    struct NonCloneValue(Vec<u8>);
+
+    #[test]
+    fn take_non_clone_value_test() {
+        let mut ht: SimpleHashTable<i64, NonCloneValue, SimpleHasher> = create_simple_hash_table(8);
+        ht.insert(1, NonCloneValue(vec![1, 2, 3]));
+        ht.insert(2, NonCloneValue(vec![4]));
+        let taken = ht.take(&1).unwrap();
+        assert_eq!(taken.0, vec![1, 2, 3]);
+        assert!(ht.take(&1).is_none());
+        assert_eq!(ht.size(), 1);
+        assert_eq!(ht.wasted_capacity(), 1);
+        assert_eq!(ht.get(&2).unwrap().0, vec![4]);
+    }
+
+    #[test]
+    fn update_test() {
+        let mut ht: SimpleHashTable<i64, i64, ConstantHasher> = create_simple_hash_table(32);
+        ht.insert(1, 1);
+        ht.insert(33, 5);
+        assert_eq!(shared_home(&ht), Some(0));
+        for _ in 0..10 {
+            assert!(ht.update(&33, |v| *v *= 2));
+        }
+        assert_eq!(ht.get(&33), Some(&5120));
+        assert_eq!(ht.get(&1), Some(&1));
+        assert!(!ht.update(&2, |v| *v = 100));
+        assert!(!ht.has(&2));
+        assert_eq!(ht.size(), 2);
+        assert_eq!(ht.wasted_capacity(), 0);
+    }
+
+    fn merge_fixture() -> (IntegerToIntegerHT, IntegerToIntegerHT) {
+        let mut a = create_integer_to_integer_ht();
+        let mut b = create_integer_to_integer_ht();
+        for i in 0..300 {
+            a.insert(i, i);
+        }
+        for i in 200..500 {
+            b.insert(i, -i);
+        }
+        (a, b)
+    }
+
+    fn assert_matches_model(ht: &IntegerToIntegerHT, model: &HashMap<i64, i64>) {
+        assert_eq!(ht.size(), model.len());
+        for (key, value) in model.iter() {
+            assert_eq!(ht.get(key), Some(value));
+        }
+    }
+
+    #[test]
+    fn merge_keep_self_test() {
+        let (mut a, b) = merge_fixture();
+        let mut model: HashMap<i64, i64> = HashMap::new();
+        for i in 200..500 {
+            model.insert(i, -i);
+        }
+        for i in 0..300 {
+            model.insert(i, i);
+        }
+        a.merge(b, MergePolicy::KeepSelf);
+        assert_matches_model(&a, &model);
+    }
+
+    #[test]
+    fn merge_keep_other_test() {
+        let (mut a, b) = merge_fixture();
+        let mut model: HashMap<i64, i64> = HashMap::new();
+        for i in 0..300 {
+            model.insert(i, i);
+        }
+        for i in 200..500 {
+            model.insert(i, -i);
+        }
+        a.merge(b, MergePolicy::KeepOther);
+        assert_matches_model(&a, &model);
+    }
+
+    #[test]
+    fn merge_with_closure_test() {
+        let (mut a, b) = merge_fixture();
+        let mut model: HashMap<i64, i64> = HashMap::new();
+        for i in 0..500 {
+            let value = match i {
+                0..=199 => i,
+                200..=299 => 1000 * i,
+                _ => -i,
+            };
+            model.insert(i, value);
+        }
+        let mut conflicts = 0;
+        a.merge_with(b, |k, mine, theirs| {
+            conflicts += 1;
+            assert_eq!(mine, -theirs);
+            1000 * *k
+        });
+        assert_eq!(conflicts, 100);
+        assert_matches_model(&a, &model);
+    }
+
+    #[test]
+    fn merge_with_panic_test() {
+        let mut a: SimpleHashTable<i64, i64, ConstantHasher> = create_simple_hash_table(64);
+        let mut b: SimpleHashTable<i64, i64, ConstantHasher> = create_simple_hash_table(64);
+        for i in 0..20 {
+            a.insert(i, i);
+            b.insert(i + 10, -i);
+        }
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            a.merge_with(b, |k, mine, _| {
+                if *k == 5 + 10 {
+                    std::panic::panic_any("merge failed");
+                }
+                mine
+            });
+        }));
+        assert!(result.is_err());
+        // the entry the closure held is lost, and only that one
+        assert_valid(&a);
+        assert_eq!(a.size(), 19);
+        assert!(!a.has(&15));
+        for i in (0..20).filter(|&i| i != 15) {
+            assert_eq!(a.get(&i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn index_test() {
+        let mut ht: SimpleHashTable<i64, i64, ConstantHasher> = create_simple_hash_table(32);
+        ht.insert(1, 10);
+        ht.insert(33, 20);
+        assert_eq!(shared_home(&ht), Some(0));
+        assert_eq!(ht[&1], 10);
+        assert_eq!(ht[&33], 20);
+        ht[&33] += 5;
+        assert_eq!(ht.get(&33), Some(&25));
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found in the table")]
+    fn index_missing_key_test() {
+        let mut ht = create_integer_to_integer_ht();
+        ht.insert(1, 10);
+        let _ = ht[&2];
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found in the table")]
+    fn index_mut_missing_key_test() {
+        let mut ht = create_integer_to_integer_ht();
+        ht[&2] = 1;
+    }
+
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    fn allocations() -> usize {
+        ALLOCATIONS.with(|count| count.get())
+    }
+
+    struct BytesHasher;
+    impl Hasher<[u8]> for BytesHasher {
+        fn hash(key: &[u8]) -> usize {
+            key.iter().fold(0usize, |acc, b| {
+                acc.wrapping_mul(31).wrapping_add(*b as usize)
+            })
+        }
+    }
+    impl Hasher<Vec<u8>> for BytesHasher {
+        fn hash(key: &Vec<u8>) -> usize {
+            <BytesHasher as Hasher<[u8]>>::hash(key)
+        }
+    }
+    impl Hasher<str> for BytesHasher {
+        fn hash(key: &str) -> usize {
+            <BytesHasher as Hasher<[u8]>>::hash(key.as_bytes())
+        }
+    }
+    impl Hasher<String> for BytesHasher {
+        fn hash(key: &String) -> usize {
+            <BytesHasher as Hasher<[u8]>>::hash(key.as_bytes())
+        }
+    }
+
+    #[test]
+    fn borrowed_str_lookup_test() {
+        let mut ht: SimpleHashTable<String, i64, BytesHasher> = create_simple_hash_table(8);
+        for i in 0..50 {
+            ht.insert(format!("key-{}", i), i);
+        }
+        let before = allocations();
+        assert_eq!(ht.get("key-7"), Some(&7));
+        assert!(ht.has("key-49"));
+        assert!(!ht.has("key-50"));
+        *ht.get_mut("key-3").unwrap() += 100;
+        assert_eq!(
+            ht.get_key_value("key-3").map(|(k, v)| (k.as_str(), *v)),
+            Some(("key-3", 103))
+        );
+        assert_eq!(allocations(), before);
+        assert!(HashTable::has(&ht, &String::from("key-4")));
+        assert_eq!(ht.remove("key-3"), Some(103));
+        assert!(!ht.has("key-3"));
+        for i in (0..50).filter(|i| *i != 3) {
+            assert_eq!(ht.get(format!("key-{}", i).as_str()), Some(&i));
+        }
+    }
+
+    #[test]
+    fn borrowed_slice_lookup_test() {
+        let mut ht: SimpleHashTable<Vec<u8>, i64, BytesHasher> = create_simple_hash_table(8);
+        for i in 0..50u8 {
+            ht.insert(vec![i, i + 1, i + 2], i as i64);
+        }
+        let before = allocations();
+        for i in 0..50u8 {
+            let key = [i, i + 1, i + 2];
+            assert_eq!(ht.get(&key[..]), Some(&(i as i64)));
+        }
+        assert!(!ht.has(&[1u8, 1, 1][..]));
+        assert_eq!(allocations(), before);
+        ht.delete(&[0u8, 1, 2][..]);
+        assert_eq!(ht.size(), 49);
+    }
+
+    #[test]
+    fn insert_many_single_resize_test() {
+        let sz_check: i64 = 500000;
+        let mut one_by_one = create_integer_to_integer_ht();
+        for i in 0..sz_check {
+            one_by_one.insert(i, i);
+        }
+        assert!(one_by_one.resize_count() > 10);
+
+        let mut ht = create_integer_to_integer_ht();
+        let pairs: Vec<(i64, i64)> = (0..sz_check).map(|i| (i, i)).collect();
+        ht.insert_many(pairs);
+        assert!(ht.resize_count() <= 1);
+        assert_eq!(ht.size(), sz_check as usize);
+        for i in 0..sz_check {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn pop_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            for i in 0..1000 {
+                ht.insert(i, i * 2);
+            }
+            let mut seen: HashSet<i64> = HashSet::new();
+            while let Some((k, v)) = ht.pop() {
+                assert_eq!(v, k * 2);
+                assert!(seen.insert(k));
+                assert_eq!(ht.size(), 1000 - seen.len());
+                if seen.len().is_multiple_of(100) {
+                    assert_valid(&ht);
+                }
+            }
+            assert_eq!(seen.len(), 1000);
+            assert!(ht.is_empty());
+            assert_eq!(ht.wasted_capacity(), 1000);
+            assert_eq!(ht.pop(), None);
+            ht.insert(5, 5);
+            assert_eq!(ht.pop(), Some((5, 5)));
+        }
+    }
+
+    #[test]
+    fn pop_after_shrink_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            for i in 0..1000 {
+                ht.insert(i, i);
+            }
+            while ht.size() > 3 {
+                ht.pop();
+            }
+            // the cursor sits far past the end of the shrunk array
+            ht.shrink_to_fit();
+            assert!(ht.capacity() < 16);
+            let mut popped = 0;
+            while let Some((k, v)) = ht.pop() {
+                assert_eq!(k, v);
+                popped += 1;
+            }
+            assert_eq!(popped, 3);
+
+            let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+                .capacity(32)
+                .capacity_policy(policy)
+                .shrink_threshold(0.1)
+                .build()
+                .unwrap();
+            for i in 0..1000 {
+                ht.insert(i, i);
+            }
+            let resizes = ht.resize_count();
+            let mut popped = 0;
+            while ht.pop().is_some() {
+                popped += 1;
+            }
+            assert_eq!(popped, 1000);
+            assert!(ht.resize_count() > resizes);
+            assert!(ht.capacity() < 64);
+        }
+    }
+
+    #[test]
+    fn random_entry_uniform_test() {
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..8 {
+            ht.insert(i, i);
+        }
+        let mut rng = rand::thread_rng();
+        let samples = 80000;
+        let mut counts = [0usize; 8];
+        for _ in 0..samples {
+            let (k, v) = ht.random_entry(&mut rng).unwrap();
+            assert_eq!(k, v);
+            counts[*k as usize] += 1;
+        }
+        let expected = samples as f64 / 8.0;
+        let chi_squared: f64 = counts
+            .iter()
+            .map(|c| (*c as f64 - expected).powi(2) / expected)
+            .sum();
+        // 7 degrees of freedom, p = 0.000001 critical value is ~40.5
+        assert!(chi_squared < 40.5, "chi squared {}", chi_squared);
+    }
+
+    #[test]
+    fn random_entry_sparse_test() {
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..10000 {
+            ht.insert(i, i);
+        }
+        for i in 0..10000 {
+            if i != 1234 && i != 4321 {
+                ht.delete(&i);
+            }
+        }
+        let mut rng = rand::thread_rng();
+        let mut counts: HashMap<i64, usize> = HashMap::new();
+        for _ in 0..2000 {
+            let (k, _) = ht.random_entry(&mut rng).unwrap();
+            *counts.entry(*k).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 2);
+        assert!(counts[&1234] > 800 && counts[&4321] > 800);
+        assert!(create_integer_to_integer_ht()
+            .random_entry(&mut rng)
+            .is_none());
+    }
+
+    #[test]
+    fn has_all_test() {
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..100 {
+            ht.insert(i, i);
+        }
+        let present: Vec<i64> = (0..100).collect();
+        assert!(ht.has_all(&present));
+        assert!(ht.missing_keys(&present).is_empty());
+
+        let mut with_gap = present.clone();
+        with_gap.insert(50, 1000);
+        assert!(!ht.has_all(&with_gap));
+        assert_eq!(ht.missing_keys(&with_gap), vec![&1000]);
+
+        let empty: Vec<i64> = Vec::new();
+        assert!(ht.has_all(&empty));
+        assert!(ht.missing_keys(&empty).is_empty());
+    }
+
+    #[test]
+    fn get_many_mut_test() {
+        let mut ht = create_integer_to_integer_ht();
+        let capacity = ht.capacity() as i64;
+        ht.insert(1, 100);
+        ht.insert(1 + capacity, 50);
+        ht.insert(1 + 2 * capacity, 0);
+        let [from, to] = ht.get_many_mut([&1, &(1 + capacity)]).unwrap();
+        *from -= 30;
+        *to += 30;
+        assert_eq!(ht.get(&1), Some(&70));
+        assert_eq!(ht.get(&(1 + capacity)), Some(&80));
+
+        let [a, b, c] = ht
+            .get_many_mut([&(1 + 2 * capacity), &1, &(1 + capacity)])
+            .unwrap();
+        *a = *b + *c;
+        assert_eq!(ht.get(&(1 + 2 * capacity)), Some(&150));
+        assert!(ht.get_many_mut([&1, &2]).is_none());
+        assert!(ht.get_many_mut([&1, &1]).is_none());
+        assert!(ht.get_many_mut::<0>([]).is_some());
+    }
+
+    #[test]
+    fn extract_if_test() {
+        for policy in POLICIES {
+            let mut ht = policy_table(policy);
+            let mut model: HashMap<i64, i64> = HashMap::new();
+            for i in 0..1000 {
+                ht.insert(i, i % 10);
+                model.insert(i, i % 10);
+            }
+            let mut extracted: Vec<(i64, i64)> = ht.extract_if(|_, v| *v < 3).collect();
+            extracted.sort();
+            let mut expected: Vec<(i64, i64)> = model
+                .iter()
+                .filter(|(_, v)| **v < 3)
+                .map(|(k, v)| (*k, *v))
+                .collect();
+            expected.sort();
+            assert_eq!(extracted, expected);
+            model.retain(|_, v| *v >= 3);
+            assert_eq!(ht.size(), model.len());
+            for (k, v) in model.iter() {
+                assert_eq!(ht.get(k), Some(v));
+            }
+        }
+    }
+
+    #[test]
+    fn extract_if_partial_test() {
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..1000 {
+            ht.insert(i, i);
+        }
+        let mut visited = 0;
+        let taken: Vec<(i64, i64)> = ht
+            .extract_if(|_, _| {
+                visited += 1;
+                true
+            })
+            .take(10)
+            .collect();
+        assert_eq!(taken.len(), 10);
+        assert_eq!(visited, 10);
+        assert_eq!(ht.size(), 990);
+        let taken_keys: HashSet<i64> = taken.iter().map(|(k, _)| *k).collect();
+        for i in 0..1000 {
+            assert_eq!(ht.has(&i), !taken_keys.contains(&i));
+        }
+    }
+
+    #[test]
+    fn into_keys_and_values_test() {
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..400 {
+            ht.insert(i, i + 10000);
+        }
+        for i in (0..400).step_by(3) {
+            ht.delete(&i);
+        }
+        let expected: Vec<i64> = (0..400).filter(|i| i % 3 != 0).collect();
+
+        let keys = ht.clone().into_keys();
+        assert_eq!(keys.len(), expected.len());
+        let mut keys: Vec<i64> = keys.collect();
+        keys.sort();
+        assert_eq!(keys, expected);
+
+        let values = ht.into_values();
+        assert_eq!(values.len(), expected.len());
+        let mut values: Vec<i64> = values.collect();
+        values.sort();
+        let expected_values: Vec<i64> = expected.iter().map(|k| k + 10000).collect();
+        assert_eq!(values, expected_values);
+    }
+
+    #[test]
+    fn append_test() {
+        let mut a = create_integer_to_integer_ht();
+        let mut b = create_integer_to_integer_ht();
+        for i in 0..300 {
+            a.insert(i, i);
+        }
+        for i in 0..3000 {
+            b.insert(i, -i);
+        }
+        for i in 0..3000 {
+            if i % 10 != 0 {
+                b.delete(&i);
+            }
+        }
+        let b_capacity = b.capacity();
+        assert_eq!(b.wasted_capacity(), 2700);
+
+        a.append(&mut b);
+
+        assert!(b.is_empty());
+        assert_eq!(b.wasted_capacity(), 0);
+        assert_eq!(b.capacity(), b_capacity);
+        assert_eq!(b.iter().count(), 0);
+        assert_eq!(a.size(), 300 + 270);
+        for i in 0..3000 {
+            if i % 10 == 0 {
+                assert_eq!(a.get(&i), Some(&-i));
+            } else if i < 300 {
+                assert_eq!(a.get(&i), Some(&i));
+            } else {
+                assert!(!a.has(&i));
+            }
+        }
+        b.insert(1, 1);
+        assert_eq!(b.get(&1), Some(&1));
+    }
+
+    #[test]
+    fn replace_key_same_cluster_test() {
+        let mut ht = create_integer_to_integer_ht();
+        let capacity = ht.capacity() as i64;
+        ht.insert(3, 30);
+        ht.insert(3 + capacity, 31);
+        ht.insert(4, 40);
+        assert_eq!(ht.replace_key(&(3 + capacity), 3 + 2 * capacity), Ok(()));
+        assert!(!ht.has(&(3 + capacity)));
+        assert_eq!(ht.get(&(3 + 2 * capacity)), Some(&31));
+        assert_eq!(ht.get(&3), Some(&30));
+        assert_eq!(ht.get(&4), Some(&40));
+        assert_eq!(ht.size(), 3);
+    }
+
+    #[test]
+    fn replace_key_with_resize_test() {
+        let mut ht = create_integer_to_integer_ht();
+        let capacity = ht.capacity() as i64;
+        for i in 0..capacity {
+            ht.insert(i, i * 10);
+        }
+        assert_eq!(ht.replace_key(&5, capacity + 5), Ok(()));
+        assert!(ht.capacity() as i64 > capacity);
+        assert_eq!(ht.size() as i64, capacity);
+        assert_eq!(ht.get(&(capacity + 5)), Some(&50));
+        assert!(!ht.has(&5));
+        for i in (0..capacity).filter(|i| *i != 5) {
+            assert_eq!(ht.get(&i), Some(&(i * 10)));
+        }
+    }
+
+    #[test]
+    fn replace_key_errors_test() {
+        let mut ht = create_integer_to_integer_ht();
+        ht.insert(1, 10);
+        ht.insert(2, 20);
+        assert_eq!(ht.replace_key(&1, 2), Err(ReplaceKeyError::KeyExists));
+        assert_eq!(ht.replace_key(&3, 4), Err(ReplaceKeyError::MissingKey));
+        assert_eq!(ht.get(&1), Some(&10));
+        assert_eq!(ht.get(&2), Some(&20));
+        assert_eq!(ht.size(), 2);
+        assert_eq!(ht.wasted_capacity(), 0);
+    }
+
+    #[test]
+    fn try_reserve_overflow_test() {
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..10 {
+            ht.insert(i, i);
+        }
+        assert_eq!(
+            ht.try_reserve(usize::MAX),
+            Err(HashTableError::CapacityOverflow)
+        );
+        assert_eq!(
+            ht.try_reserve(usize::MAX / 2),
+            Err(HashTableError::CapacityOverflow)
+        );
+        // small enough that the rounded-up capacity still fits in isize::MAX bytes
+        let slot_size = std::mem::size_of::<Slot<i64, i64>>();
+        let huge = isize::MAX as usize / slot_size / 4;
+        assert_eq!(
+            ht.try_reserve(huge),
+            Err(HashTableError::AllocError {
+                capacity: ht.capacity_for(huge + 10).next_power_of_two()
+            })
+        );
+        assert_eq!(ht.capacity(), 32);
+        assert_eq!(ht.size(), 10);
+        for i in 0..10 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+        assert_eq!(ht.try_reserve(100), Ok(()));
+        assert!(ht.capacity() >= 110);
+    }
+
+    #[test]
+    fn try_insert_or_grow_test() {
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..100 {
+            assert_eq!(ht.try_insert_or_grow(i, i), Ok(None));
+        }
+        assert_eq!(ht.try_insert_or_grow(5, 50), Ok(Some(5)));
+        assert_eq!(ht.size(), 100);
+        assert_eq!(ht.get(&5), Some(&50));
+    }
+
+    #[test]
+    fn builder_test() {
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(10)
+            .build()
+            .unwrap();
+        // rounded up to a power of two
+        assert_eq!(ht.capacity(), 16);
+        for i in 0..12 {
+            ht.insert(i, i);
+        }
+        assert_eq!(ht.capacity(), 16);
+        assert_eq!(ht.resize_count(), 0);
+        ht.insert(12, 12);
+        assert_eq!(ht.capacity(), 32);
+
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(16)
+            .max_load_factor(0.5)
+            .build()
+            .unwrap();
+        for i in 0..8 {
+            ht.insert(i, i);
+        }
+        assert_eq!(ht.capacity(), 16);
+        ht.insert(8, 8);
+        assert_eq!(ht.capacity(), 32);
+        assert_eq!(ht.resize_count(), 1);
+        ht.reserve(20);
+        assert_eq!(ht.capacity(), 64);
+        for i in 0..9 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(16)
+            .growth_factor(3.0)
+            .build()
+            .unwrap();
+        for i in 0..13 {
+            ht.insert(i, i);
+        }
+        assert_eq!(ht.capacity(), 64);
+    }
+
+    #[test]
+    fn builder_errors_test() {
+        type Builder = SimpleHashTableBuilder<i64, i64, SimpleHasher>;
+        assert_eq!(
+            Builder::new().capacity(0).build().err(),
+            Some(BuilderError::ZeroCapacity)
+        );
+        for lf in [0.0, 1.0, 1.5, -0.5] {
+            assert_eq!(
+                Builder::new().max_load_factor(lf).build().err(),
+                Some(BuilderError::InvalidMaxLoadFactor(lf))
+            );
+        }
+        assert!(Builder::new().max_load_factor(f64::NAN).build().is_err());
+        for g in [1.0, 0.5, f64::INFINITY] {
+            assert_eq!(
+                Builder::new().growth_factor(g).build().err(),
+                Some(BuilderError::InvalidGrowthFactor(g))
+            );
+        }
+    }
+
+    #[test]
+    fn hashtable_macro_test() {
+        let ht = hashtable! {1 => 10, 2 => 20, 3 => 30; SimpleHasher};
+        assert_eq!(ht.size(), 3);
+        assert_eq!(ht.capacity(), 4);
+        assert_eq!(ht.resize_count(), 0);
+        for i in 1..=3 {
+            assert_eq!(ht.get(&i), Some(&(i * 10)));
+        }
+
+        let trailing = hashtable! {1 => 10, 2 => 20,; SimpleHasher};
+        assert_eq!(trailing.size(), 2);
+
+        let inferred: IntegerToIntegerHT = hashtable! {5 => 50, 6 => 60,};
+        assert_eq!(inferred.size(), 2);
+        assert_eq!(inferred[&6], 60);
+
+        let empty: IntegerToIntegerHT = hashtable! {};
+        assert!(empty.is_empty());
+        assert_eq!(empty.capacity(), 1);
+
+        let empty_with_hasher = hashtable! {; SimpleHasher};
+        let _: &IntegerToIntegerHT = &empty_with_hasher;
+        assert!(empty_with_hasher.is_empty());
+    }
+
+    #[test]
+    fn hash_map_conversion_test() {
+        let map: HashMap<i64, i64> = (0..1000).map(|i| (i, i * 3)).collect();
+        let ht: IntegerToIntegerHT = map.clone().into();
+        assert_eq!(ht.size(), 1000);
+        assert_eq!(ht.resize_count(), 0);
+        let back: HashMap<i64, i64> = ht.into();
+        assert_eq!(back, map);
+
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(256);
+        for i in 0..200 {
+            ht.insert(i, i);
+        }
+        for i in (0..200).step_by(2) {
+            ht.remove(&i);
+        }
+        assert!(ht.wasted_capacity() > 0);
+        let map: HashMap<i64, i64> = ht.into();
+        let expected: HashMap<i64, i64> = (0..200).filter(|i| i % 2 == 1).map(|i| (i, i)).collect();
+        assert_eq!(map, expected);
+        let ht: IntegerToIntegerHT = map.into();
+        assert_eq!(ht.wasted_capacity(), 0);
+        assert_eq!(HashMap::from(ht), expected);
+    }
+
+    #[test]
+    fn from_vec_test() {
+        let mut pairs: Vec<(i64, i64)> = (0..500).map(|i| (i, i)).collect();
+        pairs.extend((0..500).step_by(5).map(|i| (i, -i)));
+        pairs.push((7, 700));
+
+        let before = allocations();
+        let ht = IntegerToIntegerHT::from(pairs);
+        // data plus the tombstone bitset, nothing from rehashing
+        assert!(allocations() - before <= 2);
+        assert_eq!(ht.resize_count(), 0);
+
+        assert_eq!(ht.size(), 500);
+        for i in 0..500 {
+            let expected = match i {
+                7 => 700,
+                i if i % 5 == 0 => -i,
+                i => i,
+            };
+            assert_eq!(ht.get(&i), Some(&expected));
+        }
+    }
+
+    // Hashes are scrambled before masking, so to lay out a table by hand this
+    // picks a hash whose home slot in a 16-slot table is `key % 16`.
+    struct HomeHasher;
+    impl Hasher<i64> for HomeHasher {
+        fn hash(key: &i64) -> usize {
+            let home = key.rem_euclid(16) as usize;
+            let policy = CapacityPolicy::NextPowerOfTwo;
+            (0..)
+                .find(|&hash| policy.home_slot(hash, 16) == home)
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn slots_test() {
+        let mut ht: SimpleHashTable<i64, i64, HomeHasher> = create_simple_hash_table(16);
+        // 3, 19 and 35 share home slot 3; 4 lands after them; 15 and 31 wrap around
+        for key in [3, 19, 35, 4, 15, 31] {
+            ht.insert(key, key);
+        }
+        ht.remove(&4);
+
+        let slots: Vec<SlotInfo<'_, i64>> = ht.slots().collect();
+        assert_eq!(slots.len(), 16);
+        let occupied: Vec<(usize, i64, usize, usize)> = slots
+            .iter()
+            .filter(|slot| slot.state == SlotState::Occupied)
+            .map(|slot| {
+                (
+                    slot.index,
+                    *slot.key.unwrap(),
+                    slot.home.unwrap(),
+                    slot.probe_distance.unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            occupied,
+            vec![
+                (0, 31, 15, 1),
+                (3, 3, 3, 0),
+                (4, 19, 3, 1),
+                (5, 35, 3, 2),
+                (15, 15, 15, 0)
+            ]
+        );
+        assert_eq!(slots[6].state, SlotState::Tombstone);
+        assert_eq!(slots[6].key, None);
+        assert_eq!(slots[1].state, SlotState::Empty);
+        assert_eq!(slots[1].probe_distance, None);
+    }
+
+    #[test]
+    fn probe_iter_test() {
+        let mut ht: SimpleHashTable<i64, i64, HomeHasher> = create_simple_hash_table(16);
+        for key in [3, 19, 35, 15, 31] {
+            ht.insert(key, key);
+        }
+        ht.remove(&19);
+
+        let probed: Vec<(usize, SlotState)> = ht.probe_iter(HomeHasher::hash(&35)).collect();
+        assert_eq!(
+            probed,
+            vec![
+                (3, SlotState::Occupied),
+                (4, SlotState::Tombstone),
+                (5, SlotState::Occupied),
+                (6, SlotState::Empty),
+            ]
+        );
+        assert_eq!(ht.get_pos_mut(&35), Some(5));
+        assert_eq!(ht.get(&35), Some(&35));
+
+        let probed: Vec<(usize, SlotState)> = ht.probe_iter(HomeHasher::hash(&31)).collect();
+        assert_eq!(
+            probed,
+            vec![
+                (15, SlotState::Occupied),
+                (0, SlotState::Occupied),
+                (1, SlotState::Empty)
+            ]
+        );
+        assert_eq!(ht.get_pos_mut(&31), Some(0));
+        assert_eq!(ht.get_pos_mut(&47), None);
+
+        for key in [3, 35, 15, 31] {
+            let pos = ht.get_pos_mut(&key).unwrap();
+            let last_occupied = ht
+                .probe_iter(HomeHasher::hash(&key))
+                .find(|&(p, _)| p == pos)
+                .unwrap();
+            assert_eq!(last_occupied, (pos, SlotState::Occupied));
+        }
+
+        let full: IntegerToIntegerHT = hashtable! {0 => 0, 1 => 1, 2 => 2};
+        assert_eq!(full.probe_iter(1).count(), 3);
+    }
+
+    thread_local! {
+        static KEY_CONVERSIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Default, PartialEq, Eq, Debug)]
+    struct CountedKey(String);
+
+    impl HashableKey for CountedKey {}
+
+    impl Borrow<str> for CountedKey {
+        fn borrow(&self) -> &str {
+            &self.0
+        }
+    }
+
+    impl From<&str> for CountedKey {
+        fn from(key: &str) -> Self {
+            KEY_CONVERSIONS.with(|count| count.set(count.get() + 1));
+            CountedKey(key.to_string())
+        }
+    }
+
+    impl Hasher<CountedKey> for BytesHasher {
+        fn hash(key: &CountedKey) -> usize {
+            <BytesHasher as Hasher<str>>::hash(&key.0)
+        }
+    }
+
+    #[test]
+    fn entry_ref_test() {
+        let conversions = || KEY_CONVERSIONS.with(|count| count.get());
+        let mut ht: SimpleHashTable<CountedKey, i64, BytesHasher> = create_simple_hash_table(8);
+
+        *ht.entry_ref("apple").or_insert(0) += 1;
+        assert_eq!(conversions(), 1);
+        for _ in 0..10 {
+            *ht.entry_ref("apple").or_insert(0) += 1;
+        }
+        assert_eq!(conversions(), 1);
+        assert_eq!(ht.get("apple"), Some(&11));
+
+        ht.entry_ref("pear")
+            .and_modify(|v| *v += 1)
+            .or_insert_with(|| 5);
+        assert_eq!(conversions(), 2);
+        ht.entry_ref("pear")
+            .and_modify(|v| *v += 1)
+            .or_insert_with(|| 5);
+        assert_eq!(conversions(), 2);
+        assert_eq!(ht.get("pear"), Some(&6));
+
+        match ht.entry_ref("plum") {
+            EntryRef::Vacant(entry) => assert_eq!(entry.key(), "plum"),
+            EntryRef::Occupied(_) => unreachable!(),
+        }
+        assert_eq!(conversions(), 2);
+        assert_eq!(ht.size(), 2);
+    }
+
+    #[test]
+    fn cursor_mut_test() {
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(64);
+        let mut model: HashMap<i64, i64> = HashMap::new();
+        for i in 0..40 {
+            ht.insert(i * 7, i);
+            model.insert(i * 7, i);
+        }
+
+        let mut cursor = ht.cursor_mut();
+        let mut visited = HashSet::new();
+        let mut remove = false;
+        while let Some((key, value)) = cursor.next() {
+            let key = *key;
+            assert!(visited.insert(key));
+            *value += 1000;
+            if remove {
+                let peeked = cursor.peek().map(|(k, _)| *k);
+                assert_eq!(
+                    cursor.remove_current(),
+                    Some((key, *model.get(&key).unwrap() + 1000))
+                );
+                assert_eq!(cursor.remove_current(), None);
+                assert_eq!(cursor.peek().map(|(k, _)| *k), peeked);
+                model.remove(&key);
+            } else {
+                model.insert(key, *model.get(&key).unwrap() + 1000);
+            }
+            remove = !remove;
+        }
+        assert_eq!(cursor.peek(), None);
+        assert_eq!(visited.len(), 40);
+
+        assert_eq!(ht.size(), 20);
+        assert_eq!(ht.wasted_capacity(), 20);
+        assert_matches_model(&ht, &model);
+    }
+
+    #[test]
+    fn partition_test() {
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(16);
+        for i in 0..300 {
+            ht.insert(i, i * 2);
+        }
+        for i in (0..300).step_by(7) {
+            ht.remove(&i);
+        }
+        let model: HashMap<i64, i64> = ht.iter().map(|(k, v)| (*k, *v)).collect();
+
+        let (ready, blocked) = ht.clone().partition(|k, _| k % 3 == 0);
+        assert_eq!(ready.size() + blocked.size(), model.len());
+        assert_eq!(ready.resize_count(), 0);
+        assert_eq!(blocked.resize_count(), 0);
+        let (expected_ready, expected_blocked): (HashMap<i64, i64>, HashMap<i64, i64>) =
+            model.iter().partition(|(k, _)| *k % 3 == 0);
+        assert_matches_model(&ready, &expected_ready);
+        assert_matches_model(&blocked, &expected_blocked);
+
+        let (all, none) = ht.partition(|_, v| *v >= 0);
+        assert!(none.is_empty());
+        assert_matches_model(&all, &model);
+    }
+
+    #[test]
+    fn min_max_key_test() {
+        let mut ht = create_integer_to_integer_ht();
+        assert_eq!(ht.min_key(), None);
+        assert_eq!(ht.max_key(), None);
+        assert_eq!(ht.min_by_key(|_, v| *v), None);
+
+        for i in 10..100 {
+            ht.insert(i, 100 - i);
+        }
+        assert_eq!(ht.min_key(), Some(&10));
+        assert_eq!(ht.max_key(), Some(&99));
+        assert_eq!(ht.min_by_key(|_, v| *v), Some((&99, &1)));
+        assert_eq!(ht.max_by_key(|_, v| *v), Some((&10, &90)));
+
+        for i in (10..20).chain(90..100) {
+            ht.remove(&i);
+        }
+        assert_eq!(ht.min_key(), Some(&20));
+        assert_eq!(ht.max_key(), Some(&89));
+        assert_eq!(ht.min_by_key(|k, _| (k % 10, *k)), Some((&20, &80)));
+        assert_eq!(ht.max_by_key(|_, v| *v), Some((&20, &80)));
+
+        for i in 20..90 {
+            ht.remove(&i);
+        }
+        assert_eq!(ht.min_key(), None);
+        assert_eq!(ht.max_key(), None);
+    }
+
+    #[test]
+    fn clone_from_test() {
+        let mut source: IntegerToIntegerHT = create_simple_hash_table(64);
+        for i in 0..50 {
+            source.insert(i, i);
+        }
+        for i in (0..50).step_by(4) {
+            source.remove(&i);
+        }
+
+        let mut snapshot: IntegerToIntegerHT = create_simple_hash_table(256);
+        snapshot.insert(1000, 1000);
+        let ptr = snapshot.data.as_ptr();
+        for round in 0..5 {
+            source.insert(100 + round, round);
+            snapshot.clone_from(&source);
+            assert_eq!(snapshot.data.as_ptr(), ptr);
+            assert!(snapshot == source);
+            assert_eq!(snapshot.capacity(), source.capacity());
+            assert_eq!(snapshot.wasted_capacity(), source.wasted_capacity());
+            assert!(snapshot
+                .slots()
+                .map(|slot| slot.state)
+                .eq(source.slots().map(|slot| slot.state)));
+            assert!(!snapshot.has(&1000));
+        }
+
+        let mut small: IntegerToIntegerHT = create_simple_hash_table(4);
+        small.clone_from(&source);
+        assert_eq!(small.capacity(), source.capacity());
+        assert!(small == source);
+        for i in (0..50).step_by(4) {
+            assert!(!small.has(&i));
+        }
+    }
+
+    struct FewBucketsHasher;
+    impl Hasher<i64> for FewBucketsHasher {
+        fn hash(key: &i64) -> usize {
+            (*key % 8) as usize
+        }
+    }
+
+    #[test]
+    fn insert_unique_unchecked_test() {
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(4);
+        for i in 0..1000 {
+            ht.insert_unique_unchecked(i, i * 2);
+        }
+        assert_eq!(ht.size(), 1000);
+        assert!(ht.resize_count() > 0);
+        for i in 0..1000 {
+            assert_eq!(ht.get(&i), Some(&(i * 2)));
+        }
+        assert!(!ht.has(&1000));
+    }
+
+    #[test]
+    fn insert_unique_unchecked_speed_test() {
+        // Separate runs of the same workload: a single cluster whose front is
+        // all tombstones. insert has to scan past them for a duplicate, while
+        // insert_unique_unchecked takes the first reusable slot.
+        let n = 2000;
+        let run = |unchecked: bool| {
+            let mut ht = SimpleHashTable::<i64, i64, ConstantHasher>::builder()
+                .capacity(4 * n)
+                .tombstone_threshold(1.0)
+                .build()
+                .unwrap();
+            for i in 0..n as i64 {
+                ht.insert(i, i);
+            }
+            for i in 0..n as i64 {
+                ht.remove(&i);
+            }
+            let start = std::time::Instant::now();
+            for i in 0..(n / 4) as i64 {
+                if unchecked {
+                    ht.insert_unique_unchecked(n as i64 + i, i);
+                } else {
+                    ht.insert(n as i64 + i, i);
+                }
+            }
+            (start.elapsed(), ht)
+        };
+        let (checked_time, checked) = run(false);
+        let (unchecked_time, unchecked) = run(true);
+
+        assert!(unchecked == checked);
+        assert!(
+            unchecked_time < checked_time,
+            "unchecked {:?} vs checked {:?}",
+            unchecked_time,
+            checked_time
+        );
+    }
+
+    #[test]
+    fn raw_entry_test() {
+        let mut ht: SimpleHashTable<String, i64, BytesHasher> = create_simple_hash_table(4);
+        for i in 0..20 {
+            let key = format!("key-{}", i);
+            let hash = <BytesHasher as Hasher<String>>::hash(&key);
+            match ht.raw_entry_mut().from_hash(hash, |k| *k == key) {
+                RawEntryMut::Vacant(entry) => {
+                    let (k, v) = entry.insert(key.clone(), i);
+                    assert_eq!(k, &key);
+                    *v += 100;
+                }
+                RawEntryMut::Occupied(_) => unreachable!(),
+            }
+        }
+        assert_eq!(ht.size(), 20);
+        for i in 0..20 {
+            let key = format!("key-{}", i);
+            assert_eq!(ht.get(key.as_str()), Some(&(i + 100)));
+            let hash = <BytesHasher as Hasher<str>>::hash(key.as_str());
+            assert_eq!(
+                ht.raw_entry().from_hash(hash, |k| *k == key),
+                Some((&key, &(i + 100)))
+            );
+        }
+
+        let key = "key-3".to_string();
+        let hash = <BytesHasher as Hasher<String>>::hash(&key);
+        assert_eq!(ht.raw_entry().from_hash(hash, |k| k == "missing"), None);
+        match ht.raw_entry_mut().from_hash(hash, |k| *k == key) {
+            RawEntryMut::Occupied(mut entry) => assert_eq!(entry.insert(7), 103),
+            RawEntryMut::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(ht.get("key-3"), Some(&7));
+        assert_eq!(ht.size(), 20);
+    }
+
+    #[test]
+    fn get_batch_prefetch_test() {
+        // The million-element table from overflow_test, far bigger than the
+        // cache, looked up at random through the prefetching batch path.
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..1000000 {
+            ht.insert(i, i);
+        }
+        let mut rng = rand::thread_rng();
+        let keys: Vec<i64> = (0..1_000_000)
+            .map(|_| rng.gen_range(0..2_000_000))
+            .collect();
+        let batched = ht.get_batch(&keys);
+        let one_by_one: Vec<Option<&i64>> = keys.iter().map(|key| ht.get(key)).collect();
+        assert_eq!(batched, one_by_one);
+        assert_eq!(
+            batched.iter().filter(|v| v.is_some()).count(),
+            keys.iter().filter(|k| **k < 1_000_000).count()
+        );
+    }
+
+    #[test]
+    fn get_batch_test() {
+        let mut rng = rand::thread_rng();
+        let mut ht = create_integer_to_integer_ht();
+        let mut model = HashMap::new();
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..5000);
+            ht.insert(key, key * 2);
+            model.insert(key, key * 2);
+        }
+        for _ in 0..800 {
+            let key = rng.gen_range(0..5000);
+            ht.remove(&key);
+            model.remove(&key);
+        }
+        let mut keys: Vec<i64> = (0..3000).map(|_| rng.gen_range(0..6000)).collect();
+        keys.extend_from_slice(&keys.clone()[..100]);
+
+        let batch = ht.get_batch(&keys);
+        assert_eq!(batch.len(), keys.len());
+        for (key, found) in keys.iter().zip(batch.iter()) {
+            assert_eq!(*found, ht.get(key));
+            assert_eq!(*found, model.get(key));
+        }
+        assert!(ht.get_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn sorted_iter_test() {
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(4);
+        for key in [42, 7, 1000, 3, 19, 88, 64, 5, 250] {
+            ht.insert(key, key % 10);
+        }
+        ht.remove(&88);
+        ht.remove(&3);
+
+        let keys: Vec<i64> = ht.sorted_iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![5, 7, 19, 42, 64, 250, 1000]);
+
+        let by_value_desc: Vec<(i64, i64)> = ht
+            .sorted_iter_by(|(ka, va), (kb, vb)| vb.cmp(va).then(ka.cmp(kb)))
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        assert_eq!(
+            by_value_desc,
+            vec![
+                (19, 9),
+                (7, 7),
+                (5, 5),
+                (64, 4),
+                (42, 2),
+                (250, 0),
+                (1000, 0)
+            ]
+        );
+        assert_eq!(IntegerToIntegerHT::default().sorted_iter().count(), 0);
+    }
+
+    #[test]
+    fn invert_test() {
+        let mut users = create_integer_to_integer_ht();
+        for user in 0..100 {
+            users.insert(user, user % 7);
+        }
+        users.remove(&0);
+        users.remove(&7);
+
+        let groups: SimpleHashTable<i64, Vec<i64>, SimpleHasher> = users.invert();
+        assert_eq!(groups.size(), 7);
+        let total: usize = groups.values().map(|group| group.len()).sum();
+        assert_eq!(total, users.size());
+        assert_eq!(groups[&0].len(), 13);
+        assert_eq!(groups[&1].len(), 15);
+        assert_eq!(groups[&6].len(), 14);
+        let order: Vec<i64> = users
+            .iter()
+            .filter(|(_, v)| **v == 3)
+            .map(|(k, _)| *k)
+            .collect();
+        assert_eq!(groups[&3], order);
+
+        let empty: SimpleHashTable<i64, Vec<i64>, SimpleHasher> =
+            create_integer_to_integer_ht().invert();
+        assert!(empty.is_empty());
+    }
+
+    #[derive(Default, Debug, PartialEq)]
+    struct Summary {
+        doubled: i64,
+    }
+
+    #[test]
+    fn map_values_test() {
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(16);
+        for i in 0..100 {
+            ht.insert(i * 3, i);
+        }
+        for i in (0..100).step_by(5) {
+            ht.remove(&(i * 3));
+        }
+        let positions: Vec<(usize, Option<i64>)> = ht
+            .slots()
+            .map(|slot| (slot.index, slot.key.copied()))
+            .collect();
+        let size = ht.size();
+
+        let mut calls = 0;
+        let mapped = ht.map_values(|k, v| {
+            calls += 1;
+            assert_eq!(*k, v * 3);
+            Summary { doubled: v * 2 }
+        });
+        assert_eq!(calls, size);
+        assert_eq!(mapped.size(), size);
+        let mapped_positions: Vec<(usize, Option<i64>)> = mapped
+            .slots()
+            .map(|slot| (slot.index, slot.key.copied()))
+            .collect();
+        assert_eq!(mapped_positions, positions);
+        for i in 0..100 {
+            let expected = if i % 5 == 0 {
+                None
+            } else {
+                Some(Summary { doubled: i * 2 })
+            };
+            assert_eq!(mapped.get(&(i * 3)), expected.as_ref());
+        }
+    }
+
+    #[test]
+    fn delete_inside_cluster_test() {
+        let mut ht: SimpleHashTable<i64, i64, IdentityHasher> = create_simple_hash_table(32);
+        ht.insert(32, 1);
+        ht.insert(64, 2);
+        ht.insert(96, 3);
+        ht.delete(&32);
+        assert_valid(&ht);
+        assert_eq!(ht.size(), 2);
+        assert_eq!(ht.get(&64), Some(&2));
+        assert_eq!(ht.get(&96), Some(&3));
+        ht.delete(&64);
+        assert_valid(&ht);
+        assert_eq!(ht.get(&96), Some(&3));
+        assert!(!ht.has(&64));
+        assert_eq!(ht.insert(96, 30), Some(3));
+        assert_eq!(ht.size(), 1);
+        ht.insert(128, 4);
+        assert_eq!(ht.get(&128), Some(&4));
+        assert_eq!(ht.remove(&96), Some(30));
+        assert_eq!(ht.get(&128), Some(&4));
+        assert_valid(&ht);
+    }
+
+    #[test]
+    fn delete_inside_cluster_random_test() {
+        let mut rng = rand::thread_rng();
+        let mut ht: SimpleHashTable<i64, i64, FewBucketsHasher> = create_simple_hash_table(32);
+        let mut model = HashMap::new();
+        // every key hashes to slot 0, so they all share one cluster
+        for _ in 0..3000 {
+            let key = rng.gen_range(0..40) * 8;
+            if rng.gen_bool(0.4) {
+                assert_eq!(ht.remove(&key), model.remove(&key));
+            } else {
+                assert_eq!(ht.insert(key, key), model.insert(key, key));
+            }
+            for (key, value) in model.iter() {
+                assert_eq!(ht.get(key), Some(value));
+            }
+            assert_eq!(ht.size(), model.len());
+            assert_valid(&ht);
+        }
+    }
+
+    #[test]
+    fn tombstone_reuse_counter_test() {
+        let mut ht: SimpleHashTable<i64, i64, FewBucketsHasher> = create_simple_hash_table(32);
+        for i in 0..10 {
+            ht.insert(i * 8, i);
+        }
+        for round in 0..10_000 {
+            let key = (round % 10) * 8;
+            assert_eq!(ht.remove(&key), Some(key / 8));
+            ht.insert(key, key / 8);
+            assert!(ht.wasted_capacity() <= 1);
+            if round % 100 == 0 {
+                assert_valid(&ht);
+            }
+        }
+        assert_eq!(ht.size(), 10);
+        assert_eq!(ht.capacity(), 32);
+        assert_eq!(ht.resize_count(), 0);
+
+        ht.remove(&0);
+        assert_eq!(ht.wasted_capacity(), 1);
+        ht.insert(200, 25);
+        assert_eq!(ht.wasted_capacity(), 0);
+    }
+
+    struct ConstantHasher;
+    impl Hasher<i64> for ConstantHasher {
+        fn hash(_key: &i64) -> usize {
+            0
+        }
+    }
+
+    #[test]
+    fn constant_hasher_stress_test() {
+        let mut ht: SimpleHashTable<i64, i64, ConstantHasher> = create_simple_hash_table(8);
+        for i in 0..600 {
+            ht.insert(i, i);
+        }
+        for i in (0..600).step_by(3) {
+            assert_eq!(ht.remove(&i), Some(i));
+        }
+        assert_valid(&ht);
+        for i in 0..600 {
+            assert_eq!(ht.get(&i), if i % 3 == 0 { None } else { Some(&i) });
+        }
+        // the last key sits at the far end of the single cluster
+        assert_eq!(
+            ht.slots()
+                .find(|s| s.key == Some(&599))
+                .unwrap()
+                .probe_distance,
+            Some(599)
+        );
+        assert!(ht.has(&599));
+        assert_eq!(ht.size(), 400);
+        // probing ends at the first empty slot past the cluster, not after a
+        // number of steps taken from the live counts
+        assert_eq!(ht.probe_iter(0).last(), Some((600, SlotState::Empty)));
+    }
+
+    struct MaxHasher;
+    impl Hasher<i64> for MaxHasher {
+        fn hash(key: &i64) -> usize {
+            usize::MAX - (*key as usize % 4)
+        }
+    }
+
+    #[test]
+    fn negative_keys_test() {
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(4);
+        let keys = [-1, -2, i64::MIN, i64::MIN + 1, i64::MAX, 0, -1000, 17];
+        for key in keys {
+            ht.insert(key, key / 2);
+        }
+        assert!(ht.resize_count() > 0);
+        for key in keys {
+            assert_eq!(ht.get(&key), Some(&(key / 2)));
+        }
+        assert_eq!(ht.remove(&i64::MIN), Some(i64::MIN / 2));
+        assert!(!ht.has(&i64::MIN));
+        assert_eq!(ht.get(&-1), Some(&0));
+
+        let mut ht: SimpleHashTable<i64, i64, MaxHasher> = create_simple_hash_table(3);
+        for i in 0..50 {
+            ht.insert(i, i);
+        }
+        for i in 0..50 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn zero_capacity_test() {
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(0);
+        assert_eq!(ht.capacity(), 0);
+        assert_eq!(ht.get(&1), None);
+        assert!(!ht.has(&1));
+        assert_eq!(ht.remove(&1), None);
+        ht.delete(&1);
+        assert_eq!(ht.pop(), None);
+        assert_eq!(ht.random_entry(&mut rand::thread_rng()), None);
+        assert_eq!(ht.iter().count(), 0);
+        assert_eq!(ht.slots().count(), 0);
+        assert_eq!(ht.probe_iter(7).count(), 0);
+        assert!(ht.cursor_mut().next().is_none());
+        assert!(format!("{:?}", ht).contains("capacity: 0"));
+
+        assert_eq!(ht.insert(1, 10), None);
+        assert_eq!(ht.get(&1), Some(&10));
+        assert_eq!(ht.capacity(), 1);
+        ht.delete(&1);
+        assert!(ht.is_empty());
+        for i in 0..20 {
+            ht.insert(i, i);
+        }
+        for i in 0..20 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(0);
+        ht.reserve(5);
+        assert!(ht.capacity() >= 5);
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(0);
+        *ht.entry(3).or_insert(0) += 1;
+        assert_eq!(ht[&3], 1);
+        let ht: IntegerToIntegerHT = Vec::new().into();
+        assert!(ht.is_empty());
+    }
+
+    #[test]
+    fn unit_capacity_test() {
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(1);
+        assert_eq!(ht.insert(5, 50), None);
+        assert_eq!(ht.capacity(), 2);
+        assert_eq!(ht.insert(5, 51), Some(50));
+        assert_eq!(ht.capacity(), 2);
+        assert_eq!(ht.remove(&5), Some(51));
+        assert_eq!(ht.get(&5), None);
+        ht.insert(6, 60);
+        ht.insert(7, 70);
+        assert_eq!(ht.get(&6), Some(&60));
+        assert_eq!(ht.get(&7), Some(&70));
+        assert_eq!(ht.size(), 2);
+    }
+
+    #[test]
+    fn constant_hasher_full_cluster_test() {
+        let mut ht = SimpleHashTable::<i64, i64, ConstantHasher>::builder()
+            .capacity(16)
+            .max_load_factor(0.99)
+            .tombstone_threshold(1.0)
+            .build()
+            .unwrap();
+        for i in 0..14 {
+            ht.insert(i, i);
+        }
+        for i in 1..14 {
+            ht.remove(&i);
+        }
+        // slot 15 is the only never-used slot left, right before the table grows
+        for i in 100..114 {
+            ht.insert(i, i);
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+        assert_eq!(ht.capacity(), 16);
+        ht.insert(200, 200);
+        assert_eq!(ht.size(), 16);
+        assert_eq!(ht.get(&0), Some(&0));
+        for i in (100..114).chain([200]) {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+    }
+
+    fn average_probe_distance<K: HashableKey, V: HashValue, H: Hasher<K>>(
+        ht: &SimpleHashTable<K, V, H>,
+    ) -> f64 {
+        let distances: Vec<usize> = ht.slots().filter_map(|slot| slot.probe_distance).collect();
+        distances.iter().sum::<usize>() as f64 / distances.len() as f64
+    }
+
+    #[test]
+    fn tombstone_reclamation_test() {
+        let mut rng = rand::thread_rng();
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(2048);
+        let mut live: Vec<i64> = Vec::new();
+        for _ in 0..1000 {
+            let key = rng.gen_range(0..1_000_000_000);
+            if ht.insert(key, key).is_none() {
+                live.push(key);
+            }
+        }
+        for round in 0..50_000 {
+            let victim = live.swap_remove(rng.gen_range(0..live.len()));
+            ht.remove(&victim);
+            loop {
+                let key = rng.gen_range(0..1_000_000_000);
+                if ht.insert(key, key).is_none() {
+                    live.push(key);
+                    break;
+                }
+            }
+            assert!(ht.wasted_capacity() as f64 <= 0.25 * ht.capacity() as f64 + 1.0);
+            if round % 1000 == 0 {
+                assert_valid(&ht);
+            }
+        }
+        assert_eq!(ht.size(), live.len());
+        assert_eq!(ht.capacity(), 2048);
+        assert!(ht.resize_count() > 0);
+        assert!(average_probe_distance(&ht) < 2.0);
+        for key in live.iter() {
+            assert_eq!(ht.get(key), Some(key));
+        }
+    }
+
+    #[test]
+    fn tombstone_threshold_config_test() {
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(128)
+            .tombstone_threshold(0.1)
+            .build()
+            .unwrap();
+        assert_eq!(ht.tombstone_threshold(), 0.1);
+        for i in 0..20 {
+            ht.insert(i, i);
+        }
+        for i in 0..13 {
+            ht.remove(&i);
+        }
+        assert_eq!(ht.wasted_capacity(), 13);
+        ht.insert(50, 50);
+        assert_eq!(ht.wasted_capacity(), 0);
+        assert_eq!(ht.capacity(), 128);
+
+        ht.set_tombstone_threshold(1.0);
+        for i in 13..20 {
+            ht.remove(&i);
+        }
+        ht.insert(51, 51);
+        assert_eq!(ht.wasted_capacity(), 7);
+
+        assert_eq!(
+            SimpleHashTableBuilder::<i64, i64, SimpleHasher>::new()
+                .tombstone_threshold(0.0)
+                .build()
+                .err(),
+            Some(BuilderError::InvalidTombstoneThreshold(0.0))
+        );
+        let result = std::panic::catch_unwind(|| {
+            create_integer_to_integer_ht().set_tombstone_threshold(1.5);
+        });
+        assert!(result.is_err());
+    }
+
+    #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+    struct NonZeroKey(std::num::NonZeroU64);
+
+    impl HashableKey for NonZeroKey {}
+
+    struct NonZeroHasher;
+    impl Hasher<NonZeroKey> for NonZeroHasher {
+        fn hash(key: &NonZeroKey) -> usize {
+            key.0.get() as usize
+        }
+    }
+
+    #[derive(PartialEq, Debug)]
+    struct NoDefaultValue(std::num::NonZeroU64);
+
+    #[test]
+    fn no_default_bounds_test() {
+        let key = |n: u64| NonZeroKey(std::num::NonZeroU64::new(n).unwrap());
+        let value = |n: u64| NoDefaultValue(std::num::NonZeroU64::new(n).unwrap());
+        let mut ht: SimpleHashTable<NonZeroKey, NoDefaultValue, NonZeroHasher> =
+            create_simple_hash_table(2);
+        for n in 1..100 {
+            ht.insert(key(n), value(n * 2));
+        }
+        assert!(ht.resize_count() > 0);
+        for n in (1..100).step_by(2) {
+            assert_eq!(ht.remove(&key(n)), Some(value(n * 2)));
+        }
+        ht.shrink_to_fit();
+        for n in 1..100 {
+            let expected = if n % 2 == 1 { None } else { Some(value(n * 2)) };
+            assert_eq!(ht.get(&key(n)), expected.as_ref());
+        }
+        assert_eq!(ht.drain().count(), 49);
+    }
+
+    fn bucket_chi_squared<I: Iterator<Item = i64>>(keys: I, buckets: usize) -> f64 {
+        let mut counts = vec![0usize; buckets];
+        let mut total = 0;
+        for key in keys {
+            counts[SimpleHasher::hash(&key) % buckets] += 1;
+            total += 1;
+        }
+        let expected = total as f64 / buckets as f64;
+        counts
+            .iter()
+            .map(|c| (*c as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    #[test]
+    fn simple_hasher_distribution_test() {
+        // 15 degrees of freedom, p = 0.000001 critical value is ~53.6
+        for keys in [-8000..0, 0..8000, -4000..4000] {
+            let chi_squared = bucket_chi_squared(keys.clone(), 16);
+            assert!(
+                chi_squared < 53.6,
+                "{:?}: chi squared {}",
+                keys,
+                chi_squared
+            );
+        }
+        let strided = (0..8000).map(|i| i * 1024 - 4_096_000);
+        assert!(bucket_chi_squared(strided, 16) < 53.6);
+
+        // fixed values, the same on 32 and 64 bit targets
+        assert_eq!(SimpleHasher::hash(&-5), 3042109070);
+        assert_eq!(SimpleHasher::hash(&1), 1184437240);
+        assert_ne!(SimpleHasher::hash(&-1), usize::MAX);
+        assert!(SimpleHasher::hash(&i64::MIN) <= u32::MAX as usize);
+        assert_eq!(IdentityHasher::hash(&-5), usize::MAX - 4);
+        assert_eq!(IdentityHasher::hash(&7), 7);
+    }
+
+    #[test]
+    fn default_load_factor_probe_length_test() {
+        let mut rng = rand::thread_rng();
+        let mut ht = create_integer_to_integer_ht();
+        assert_eq!(ht.max_load_factor(), 0.75);
+        for _ in 0..50_000 {
+            let key = rng.gen_range(0..i64::MAX);
+            ht.insert(key, key);
+            assert!(ht.size() as f64 <= ht.capacity() as f64 * 0.75 + 1.0);
+        }
+        assert!(average_probe_distance(&ht) < 2.5);
+
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .max_load_factor(0.5)
+            .build()
+            .unwrap();
+        for _ in 0..50_000 {
+            let key = rng.gen_range(0..i64::MAX);
+            ht.insert(key, key);
+        }
+        assert!(average_probe_distance(&ht) < 1.0);
+        ht.set_max_load_factor(0.9);
+        assert_eq!(ht.max_load_factor(), 0.9);
+    }
+
+    #[test]
+    fn try_grow_test() {
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..10 {
+            ht.insert(i, i);
+        }
+        assert_eq!(ht.try_grow(8), Ok(()));
+        assert_eq!(ht.capacity(), 32);
+        assert_eq!(ht.try_grow(100), Ok(()));
+        assert_eq!(ht.capacity(), 128);
+
+        let slot_size = std::mem::size_of::<Slot<i64, i64>>();
+        assert_eq!(
+            ht.try_grow(usize::MAX / slot_size + 1),
+            Err(HashTableError::CapacityOverflow)
+        );
+        assert_eq!(
+            ht.try_grow(isize::MAX as usize / slot_size / 2),
+            Err(HashTableError::AllocError {
+                capacity: (isize::MAX as usize / slot_size / 2).next_power_of_two()
+            })
+        );
+        assert_eq!(ht.capacity(), 128);
+        for i in 0..10 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+        let err: TryReserveError = HashTableError::CapacityOverflow;
+        assert_eq!(err.to_string(), "capacity overflow");
+    }
+
+    #[test]
+    fn capacity_policy_test() {
+        let growth = |policy: CapacityPolicy| {
+            let mut ht = SimpleHashTable::<i64, i64, IdentityHasher>::builder()
+                .capacity(10)
+                .capacity_policy(policy)
+                .build()
+                .unwrap();
+            assert_eq!(ht.capacity_policy(), policy);
+            let mut capacities = vec![ht.capacity()];
+            for i in 0..150 {
+                ht.insert(i, i);
+                if ht.capacity() != *capacities.last().unwrap() {
+                    capacities.push(ht.capacity());
+                }
+            }
+            for i in 0..150 {
+                assert_eq!(ht.get(&i), Some(&i));
+            }
+            assert_valid(&ht);
+            capacities
+        };
+        assert_eq!(
+            growth(CapacityPolicy::NextPowerOfTwo),
+            vec![16, 32, 64, 128, 256]
+        );
+        assert_eq!(
+            growth(CapacityPolicy::NextPrime),
+            vec![11, 23, 47, 107, 239]
+        );
+        assert_eq!(
+            SimpleHashTable::<i64, i64, SimpleHasher>::default().capacity_policy(),
+            CapacityPolicy::NextPowerOfTwo
+        );
+
+        // keys sharing a residue mod 16 collide under plain masking, not mod a prime
+        let mut ht = SimpleHashTable::<i64, i64, IdentityHasher>::builder()
+            .capacity(128)
+            .capacity_policy(CapacityPolicy::NextPrime)
+            .build()
+            .unwrap();
+        for i in 0..60 {
+            ht.insert(i * 16, i);
+        }
+        assert_eq!(ht.capacity(), 131);
+        assert!(ht.slots().all(|slot| slot.probe_distance.unwrap_or(0) == 0));
+        let mut shrunk = ht.clone();
+        shrunk.shrink_to_fit();
+        assert!(is_prime(shrunk.capacity()));
+        assert!(shrunk == ht);
+    }
+
+    #[test]
+    fn next_prime_test() {
+        let naive = |n: usize| {
+            n >= 2
+                && (2..n)
+                    .take_while(|d| d * d <= n)
+                    .all(|d| !n.is_multiple_of(d))
+        };
+        for n in 0..5000 {
+            assert_eq!(is_prime(n), naive(n), "{}", n);
+        }
+        assert!(PRIME_LADDER.iter().all(|&p| naive(p)));
+        assert!(PRIME_LADDER.windows(2).all(|w| w[0] < w[1]));
+        // Carmichael numbers and a large Mersenne prime
+        assert!(!is_prime(561));
+        assert!(!is_prime(41041));
+        assert!(is_prime((1 << 61) - 1));
+        assert!(!is_prime((1 << 61) + 1));
+
+        assert_eq!(next_prime(0), Some(3));
+        assert_eq!(next_prime(12), Some(17));
+        assert_eq!(next_prime(17), Some(17));
+        let past_ladder = next_prime(7199370).unwrap();
+        assert!(naive(past_ladder));
+        assert!((7199370..past_ladder).all(|n| !naive(n)));
+        assert_eq!(CapacityPolicy::NextPrime.round(0), Some(0));
+        assert_eq!(CapacityPolicy::NextPowerOfTwo.round(usize::MAX), None);
+    }
+
+    #[test]
+    fn rehash_capacity_test() {
+        assert_eq!(rehash_capacity(16, 12, 11, 0), None);
+        assert_eq!(rehash_capacity(16, 12, 12, 0), Some(32));
+        // mostly tombstones: rehash without growing
+        assert_eq!(rehash_capacity(16, 12, 5, 7), Some(16));
+        assert_eq!(rehash_capacity(0, 0, 0, 0), Some(1));
+    }
+
+    #[test]
+    fn grown_capacity_overflow_test() {
+        assert_eq!(grown_capacity(16, 2.0), Some(33));
+        assert_eq!(grown_capacity(0, 2.0), Some(1));
+        assert_eq!(grown_capacity(usize::MAX / 2, 2.0), None);
+        assert_eq!(grown_capacity(usize::MAX, 1.5), None);
+        assert!(grown_capacity(usize::MAX / 4, 2.0).unwrap() > usize::MAX / 4);
+
+        let mut ht = create_integer_to_integer_ht();
+        for i in 0..10 {
+            ht.insert(i, i);
+        }
+        assert_eq!(
+            ht.try_resize(8),
+            Err(HashTableError::CapacityTooSmall {
+                capacity: 8,
+                entries: 10
+            })
+        );
+        assert_eq!(ht.capacity(), 32);
+        assert_eq!(ht.try_resize(10), Ok(()));
+        for i in 0..10 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+        assert_eq!(
+            HashTableError::CapacityTooSmall {
+                capacity: 9,
+                entries: 10
+            }
+            .to_string(),
+            "9 slots can't hold 10 entries"
+        );
+    }
+
+    #[test]
+    fn has_matches_get_property_test() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let mut ht: SimpleHashTable<i64, i64, FewBucketsHasher> = create_simple_hash_table(4);
+            let mut model = HashMap::new();
+            for _ in 0..500 {
+                let key = rng.gen_range(0..64);
+                match rng.gen_range(0..3) {
+                    0 => assert_eq!(ht.remove(&key), model.remove(&key)),
+                    1 => assert_eq!(ht.insert(key, key), model.insert(key, key)),
+                    _ => {
+                        ht.entry(key).or_insert(key);
+                        model.entry(key).or_insert(key);
+                    }
+                }
+                for probe in 0..64 {
+                    assert_eq!(ht.has(&probe), ht.get(&probe).is_some());
+                    assert_eq!(ht.has(&probe), model.contains_key(&probe));
+                }
+                assert_valid(&ht);
+            }
+        }
+    }
+
+    #[test]
+    fn tombstone_dominated_rehash_test() {
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(64)
+            .tombstone_threshold(1.0)
+            .build()
+            .unwrap();
+        let live = 20;
+        for i in 0..live {
+            ht.insert(i, i);
+        }
+        for i in live..1_000_000 {
+            ht.remove(&(i - live));
+            ht.insert(i, i);
+            if i % 10_000 == 0 {
+                assert_valid(&ht);
+            }
+        }
+        assert_eq!(ht.size(), live as usize);
+        assert!(ht.capacity() <= 64, "capacity {}", ht.capacity());
+        assert!(ht.resize_count() > 0);
+        for i in 1_000_000 - live..1_000_000 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+    }
+
+    struct Opaque {
+        bytes: Vec<u8>,
+    }
+
+    fn assert_valid<K: HashableKey, V: HashValue, H: Hasher<K>>(ht: &SimpleHashTable<K, V, H>) {
+        assert_eq!(ht.debug_validate(), Ok(()));
+    }
+
+    enum Corruption {
+        SlotsUsed,
+        DeletedSlots,
+        Move { from: usize, to: usize },
+        Hash(usize),
+    }
+
+    // Test-only hook that breaks one invariant behind the table's back.
+    fn corrupt<K: HashableKey, V: HashValue, H: Hasher<K>>(
+        ht: &mut SimpleHashTable<K, V, H>,
+        corruption: Corruption,
+    ) {
+        match corruption {
+            Corruption::SlotsUsed => ht.slots_used += 1,
+            Corruption::DeletedSlots => ht.deleted_slots += 1,
+            Corruption::Move { from, to } => ht.data.swap(from, to),
+            Corruption::Hash(pos) => ht.data[pos].occupied_mut().unwrap().hash ^= 1,
+        }
+    }
+
+    #[test]
+    fn debug_validate_test() {
+        let fixture = || {
+            let mut ht: SimpleHashTable<i64, i64, HomeHasher> = create_simple_hash_table(16);
+            for i in 0..6 {
+                ht.insert(i, i);
+            }
+            ht.remove(&2);
+            ht
+        };
+        assert_eq!(fixture().debug_validate(), Ok(()));
+        assert_eq!(
+            SimpleHashTable::<i64, i64, SimpleHasher>::default().debug_validate(),
+            Ok(())
+        );
+
+        let mut ht = fixture();
+        corrupt(&mut ht, Corruption::SlotsUsed);
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::SlotsUsedMismatch {
+                recorded: 6,
+                actual: 5
+            })
+        );
+
+        let mut ht = fixture();
+        corrupt(&mut ht, Corruption::DeletedSlots);
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::DeletedSlotsMismatch {
+                recorded: 2,
+                actual: 1
+            })
+        );
+
+        // key 5 moved past the empty slot 6 can't be found from slot 5
+        let mut ht = fixture();
+        corrupt(&mut ht, Corruption::Move { from: 5, to: 7 });
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::UnreachableKey { index: 7 })
+        );
+        assert_eq!(
+            InvariantViolation::UnreachableKey { index: 7 }.to_string(),
+            "the key in slot 7 can't be reached from its home slot"
+        );
+
+        let mut ht = fixture();
+        corrupt(&mut ht, Corruption::Hash(3));
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::StaleHash { index: 3 })
+        );
+    }
+
+    thread_local! {
+        static HASH_CALLS: Cell<usize> = const { Cell::new(0) };
+        static KEY_EQ_CALLS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn hash_calls() -> usize {
+        HASH_CALLS.with(|count| count.replace(0))
+    }
+
+    struct CountingHasher;
+    impl Hasher<i64> for CountingHasher {
+        fn hash(key: &i64) -> usize {
+            HASH_CALLS.with(|count| count.set(count.get() + 1));
+            SimpleHasher::hash(key)
+        }
+    }
+
+    #[derive(Debug)]
+    struct EqCountedKey(i64);
+
+    impl PartialEq for EqCountedKey {
+        fn eq(&self, other: &Self) -> bool {
+            KEY_EQ_CALLS.with(|count| count.set(count.get() + 1));
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for EqCountedKey {}
+
+    impl HashableKey for EqCountedKey {}
+
+    impl Hasher<EqCountedKey> for IdentityHasher {
+        fn hash(key: &EqCountedKey) -> usize {
+            key.0 as usize
+        }
+    }
+
+    #[test]
+    fn cached_hash_test() {
+        let mut ht: SimpleHashTable<i64, i64, CountingHasher> = create_simple_hash_table(16);
+        hash_calls();
+        for i in 0..1000 {
+            ht.insert(i, i);
+        }
+        assert!(ht.resize_count() >= 6);
+        assert_eq!(hash_calls(), 1000);
+
+        // resizing re-derives every position from the cached hashes
+        ht.reserve(10_000);
+        ht.shrink_to_fit();
+        let mut copy = ht.clone();
+        copy.try_resize(4096).unwrap();
+        assert_eq!(hash_calls(), 0);
+        for i in 0..600 {
+            ht.remove(&i);
+        }
+        let resizes = ht.resize_count();
+        ht.insert(-1, -1);
+        assert!(ht.resize_count() > resizes);
+        assert_eq!(hash_calls(), 601);
+        assert_eq!(ht.get(&999), Some(&999));
+        assert_eq!(hash_calls(), 1);
+        let mut entry_table: SimpleHashTable<i64, i64, CountingHasher> =
+            create_simple_hash_table(1);
+        for i in 0..100 {
+            *entry_table.entry(i % 10).or_insert(0) += 1;
+        }
+        assert_eq!(hash_calls(), 100);
+
+        // distinct hashes sharing home slot 0: the probe walks the whole
+        // cluster but only compares the key whose hash matches
+        let mut ht = SimpleHashTable::<EqCountedKey, i64, IdentityHasher>::builder()
+            .capacity(131)
+            .capacity_policy(CapacityPolicy::NextPrime)
+            .build()
+            .unwrap();
+        for i in 0..50 {
+            ht.insert(EqCountedKey(i * 131), i);
+        }
+        KEY_EQ_CALLS.with(|count| count.set(0));
+        assert_eq!(ht.get(&EqCountedKey(49 * 131)), Some(&49));
+        assert_eq!(ht.get(&EqCountedKey(50 * 131)), None);
+        assert_eq!(KEY_EQ_CALLS.with(|count| count.get()), 1);
+        assert_valid(&ht);
+    }
+
+    #[test]
+    fn slot_handle_test() {
+        let mut ht = create_integer_to_integer_ht();
+        let (previous, handle) = ht.insert_with_handle(7, 70);
+        assert_eq!(previous, None);
+        assert_eq!(ht.get_by_handle(handle), Some(&70));
+        assert_eq!(ht.handle_of(&7), Some(handle));
+        assert_eq!(ht.handle_of(&8), None);
+
+        for i in 100..110 {
+            ht.insert(i, i);
+        }
+        assert_eq!(ht.get_by_handle(handle), Some(&70));
+        *ht.get_by_handle_mut(handle).unwrap() += 1;
+        assert_eq!(ht.get(&7), Some(&71));
+        assert_eq!(ht.insert_with_handle(7, 72), (Some(71), handle));
+
+        ht.reserve(1000);
+        assert_eq!(ht.get_by_handle(handle), None);
+        assert_eq!(ht.get_by_handle_mut(handle), None);
+        let handle = ht.handle_of(&7).unwrap();
+        assert_eq!(ht.get_by_handle(handle), Some(&72));
+
+        // removing an entry only invalidates the handles to it
+        let other = ht.handle_of(&100).unwrap();
+        ht.remove(&7);
+        assert_eq!(ht.get_by_handle(handle), None);
+        assert_eq!(ht.get_by_handle(other), Some(&100));
+        // nor does a new entry that lands in the freed slot revive it
+        ht.insert(7, 73);
+        assert_eq!(ht.handle_of(&7).map(|h| h.index), Some(handle.index));
+        assert_eq!(ht.get_by_handle(handle), None);
+        ht.remove(&101);
+        assert_eq!(ht.get_by_handle(other), Some(&100));
+        *ht.get_by_handle_mut(other).unwrap() += 1;
+        assert_eq!(ht.get(&100), Some(&101));
+        ht.clear();
+        let (_, handle) = ht.insert_with_handle(1, 1);
+        ht.clear();
+        assert_eq!(ht.get_by_handle(handle), None);
+    }
+
+    #[test]
+    fn ordered_float_key_test() {
+        let mut ht: SimpleHashTable<OrderedFloatKey, i64, SimpleHasher> =
+            create_simple_hash_table(8);
+        assert_eq!(ht.insert(OrderedFloatKey(f64::NAN), 1), None);
+        assert_eq!(ht.insert(OrderedFloatKey(-f64::NAN), 2), Some(1));
+        assert_eq!(ht.size(), 1);
+        assert_eq!(ht.get(&OrderedFloatKey(f64::NAN)), Some(&2));
+
+        ht.insert(OrderedFloatKey(0.0), 3);
+        assert_eq!(ht.get(&OrderedFloatKey(-0.0)), Some(&3));
+        ht.insert(1.5.into(), 4);
+        assert_eq!(ht.size(), 3);
+
+        assert_eq!(ht.remove(&OrderedFloatKey(f64::NAN)), Some(2));
+        assert_eq!(ht.remove(&OrderedFloatKey(-0.0)), Some(3));
+        assert_eq!(ht.size(), 1);
+        assert!(!ht.has(&OrderedFloatKey(f64::NAN)));
+        assert_eq!(ht.get(&OrderedFloatKey(1.5)), Some(&4));
+    }
+
+    #[test]
+    fn unit_and_non_display_values_test() {
+        let mut ht: SimpleHashTable<i64, (), SimpleHasher> = create_simple_hash_table(4);
+        for i in 0..100 {
+            assert_eq!(ht.insert(i, ()), None);
+        }
+        assert_eq!(ht.insert(5, ()), Some(()));
+        assert!(ht.has(&42));
+        ht.delete(&42);
+        assert!(!ht.has(&42));
+        assert_eq!(ht.size(), 99);
+
+        let mut bytes: SimpleHashTable<i64, Vec<u8>, SimpleHasher> = create_simple_hash_table(4);
+        bytes.insert(1, vec![1, 2, 3]);
+        bytes.get_mut(&1).unwrap().push(4);
+        assert_eq!(bytes.get(&1), Some(&vec![1, 2, 3, 4]));
+
+        let mut opaque: SimpleHashTable<i64, Opaque, SimpleHasher> = create_simple_hash_table(4);
+        opaque.insert(
+            7,
+            Opaque {
+                bytes: b"opaque".to_vec(),
+            },
+        );
+        assert_eq!(opaque.get(&7).map(|v| v.bytes.len()), Some(6));
+    }
+
+    fn pending_migration<K: HashableKey, V: HashValue, H: Hasher<K>>(
+        ht: &SimpleHashTable<K, V, H>,
+    ) -> usize {
+        ht.old
+            .iter()
+            .filter(|slot| matches!(slot, Slot::Occupied(_)))
+            .count()
+    }
+
+    #[test]
+    fn incremental_resize_test() {
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(64)
+            .migration_batch(4)
+            .build()
+            .unwrap();
+        let mut i = 0;
+        while !ht.is_migrating() {
+            ht.insert(i, i);
+            i += 1;
+        }
+        assert_eq!(ht.resize_count(), 1);
+        assert_eq!(ht.capacity(), 128);
+        // only the insert that started the migration is in the new array
+        assert_eq!(pending_migration(&ht), i as usize - 1);
+        assert_valid(&ht);
+
+        // until some keys have been migrated and most haven't
+        while pending_migration(&ht) == i as usize - 1 {
+            ht.insert(i, i);
+            i += 1;
+        }
+        assert!(pending_migration(&ht) > 40);
+        assert_valid(&ht);
+        for k in 0..i {
+            assert_eq!(ht.get(&k), Some(&k));
+            assert!(ht.has(&k));
+        }
+        assert_eq!(ht.iter().count(), i as usize);
+        let handle = ht.handle_of(&(i - 1)).unwrap();
+        assert_eq!(ht.get_by_handle(handle), Some(&(i - 1)));
+
+        // mutating an unmigrated key pulls it over with the batch
+        let before = pending_migration(&ht);
+        *ht.get_mut(&0).unwrap() += 100;
+        assert!(before - pending_migration(&ht) <= 4);
+        assert_eq!(ht.remove(&1), Some(1));
+        assert_eq!(ht.insert(2, 20), Some(2));
+        assert_valid(&ht);
+        assert_eq!(ht.get(&0), Some(&100));
+        assert!(!ht.has(&1));
+        assert_eq!(ht.size(), i as usize - 1);
+
+        while ht.is_migrating() {
+            ht.insert(i, i);
+            assert_valid(&ht);
+            i += 1;
+        }
+        assert_eq!(ht.resize_count(), 1);
+        for k in 3..i {
+            assert_eq!(ht.get(&k), Some(&k));
+        }
+
+        assert_eq!(
+            SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+                .migration_batch(0)
+                .build()
+                .err(),
+            Some(BuilderError::ZeroMigrationBatch)
+        );
+        let ht: IntegerToIntegerHT = SimpleHashTable::builder()
+            .incremental_resize(true)
+            .build()
+            .unwrap();
+        assert_eq!(ht.config.migration_batch, Some(DEFAULT_MIGRATION_BATCH));
+    }
+
+    #[test]
+    fn failed_reserve_keeps_migration_test() {
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(64)
+            .migration_batch(4)
+            .build()
+            .unwrap();
+        let mut i = 0;
+        while !ht.is_migrating() {
+            ht.insert(i, i);
+            i += 1;
+        }
+        let pending = pending_migration(&ht);
+        let capacity = ht.capacity();
+        assert_eq!(
+            ht.try_reserve(usize::MAX / 2),
+            Err(HashTableError::CapacityOverflow)
+        );
+        let slot_size = std::mem::size_of::<Slot<i64, i64>>();
+        let huge = isize::MAX as usize / slot_size / 4;
+        assert!(matches!(
+            ht.try_reserve(huge),
+            Err(HashTableError::AllocError { .. })
+        ));
+        assert!(ht.is_migrating());
+        assert_eq!(pending_migration(&ht), pending);
+        assert_eq!(ht.capacity(), capacity);
+        assert_valid(&ht);
+        for k in 0..i {
+            assert_eq!(ht.get(&k), Some(&k));
+        }
+    }
+
+    #[test]
+    fn incremental_insert_moves_at_most_a_batch_test() {
+        let batch = 1;
+        let mut rng = StdRng::seed_from_u64(94);
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(4)
+            .migration_batch(batch)
+            .build()
+            .unwrap();
+        migrated_entries();
+        let mut largest = 0;
+        for i in 0..50_000 {
+            ht.insert(i, i);
+            largest = std::cmp::max(largest, migrated_entries());
+            if rng.gen_range(0..4) == 0 {
+                ht.remove(&rng.gen_range(0..=i));
+                largest = std::cmp::max(largest, migrated_entries());
+            }
+        }
+        assert!(largest <= batch, "an operation moved {} entries", largest);
+        assert!(ht.resize_count() > 10);
+        assert_valid(&ht);
+
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(4);
+        let mut largest = 0;
+        for i in 0..50_000 {
+            let resizes = ht.resize_count();
+            let size = ht.size();
+            ht.insert(i, i);
+            if ht.resize_count() > resizes {
+                largest = std::cmp::max(largest, size);
+            }
+        }
+        assert!(largest > 10_000);
+    }
+
+    #[test]
+    fn auto_shrink_test() {
+        let build = |incremental: bool| {
+            SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+                .capacity(16)
+                .shrink_threshold(0.125)
+                .incremental_resize(incremental)
+                .build()
+                .unwrap()
+        };
+        for incremental in [false, true] {
+            let mut ht = build(incremental);
+            for i in 0..100_000 {
+                ht.insert(i, i);
+            }
+            let peak = ht.capacity();
+            assert!(peak >= 131_072);
+            // drain all but every thousandth key
+            for i in (0..100_000).filter(|i| i % 1000 != 0) {
+                assert_eq!(ht.remove(&i), Some(i));
+            }
+            assert_eq!(ht.size(), 100);
+            assert!(ht.capacity() <= 512, "capacity {}", ht.capacity());
+            assert!(ht.resize_count() > 20);
+            for i in (0..100_000).step_by(1000) {
+                assert_eq!(ht.get(&i), Some(&i));
+            }
+            assert_valid(&ht);
+
+            // retain shrinks as well, but never below the minimum capacity
+            ht.retain(|k, _| *k < 3000);
+            assert_eq!(ht.size(), 3);
+            assert_eq!(ht.capacity(), 16);
+            assert!(ht.has(&2000));
+        }
+
+        // the rebuild drops tombstones
+        let mut ht = build(false);
+        for i in 0..1000 {
+            ht.insert(i, i);
+        }
+        let resizes = ht.resize_count();
+        let mut i = 0;
+        while ht.resize_count() == resizes {
+            ht.remove(&i);
+            i += 1;
+        }
+        assert_eq!(ht.wasted_capacity(), 0);
+        assert_eq!(ht.size(), 1000 - i as usize);
+    }
+
+    #[test]
+    fn auto_shrink_hysteresis_test() {
+        let mut ht = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(1024)
+            .min_capacity(16)
+            .shrink_threshold(0.125)
+            .build()
+            .unwrap();
+        // churning right at either threshold changes the capacity at most once
+        let churn = |ht: &mut SimpleHashTable<i64, i64, SimpleHasher>, n: i64| {
+            let mut capacities = vec![ht.capacity()];
+            for round in 0..1000 {
+                ht.remove(&(round % n));
+                ht.insert(round % n, round);
+                if capacities.last() != Some(&ht.capacity()) {
+                    capacities.push(ht.capacity());
+                }
+            }
+            capacities
+        };
+        for i in 0..128 {
+            ht.insert(i, i);
+        }
+        assert_eq!(churn(&mut ht, 128), vec![1024, 512]);
+        for i in 128..383 {
+            ht.insert(i, i);
+        }
+        assert_eq!(churn(&mut ht, 383), vec![512]);
+        assert_valid(&ht);
+
+        type Builder = SimpleHashTableBuilder<i64, i64, SimpleHasher>;
+        for threshold in [0.0, -0.1, 0.375, 0.5, f64::NAN] {
+            assert!(matches!(
+                Builder::new().shrink_threshold(threshold).build().err(),
+                Some(BuilderError::InvalidShrinkThreshold(_))
+            ));
+        }
+        assert!(matches!(
+            Builder::new()
+                .shrink_threshold(0.1)
+                .growth_factor(8.0)
+                .build()
+                .err(),
+            Some(BuilderError::InvalidShrinkThreshold(_))
+        ));
+        assert!(Builder::new().shrink_threshold(0.3).build().is_ok());
+    }
+
+    #[test]
+    fn probe_stats_test() {
+        let ht: IntegerToIntegerHT = create_simple_hash_table(16);
+        assert_eq!(ht.probe_stats(), ProbeStats::default());
+        assert_eq!(ht.probe_stats().mean, 0.0);
+
+        // every key has the same home, so the i-th insert lands i slots away
+        let mut ht: SimpleHashTable<i64, i64, ConstantHasher> = create_simple_hash_table(32);
+        for i in 0..10 {
+            ht.insert(i, i);
+        }
+        let stats = ht.probe_stats();
+        assert_eq!(stats.entries, 10);
+        assert_eq!((stats.min, stats.max), (0, 9));
+        assert_eq!(stats.mean, 4.5);
+        assert_eq!(stats.histogram, [1, 1, 1, 1, 1, 1, 1, 3]);
+        assert_eq!(stats.longest_run, 10);
+        let mut distances: Vec<usize> = ht.slots().filter_map(|slot| slot.probe_distance).collect();
+        distances.sort_unstable();
+        assert_eq!(distances, (0..10).collect::<Vec<_>>());
+
+        // runs wrap around the end of the table, and tombstones end them
+        let mut ht: SimpleHashTable<i64, i64, HomeHasher> = create_simple_hash_table(16);
+        for key in [14, 15, 0, 1, 5] {
+            ht.insert(key, key);
+        }
+        let stats = ht.probe_stats();
+        assert_eq!((stats.entries, stats.max, stats.longest_run), (5, 0, 4));
+        ht.remove(&15);
+        assert_eq!(ht.probe_stats().longest_run, 2);
+    }
+
+    #[test]
+    fn memory_usage_test() {
+        let slot_size = std::mem::size_of::<Slot<i64, i64>>();
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(16);
+        assert_eq!(
+            ht.memory_usage(),
+            MemoryUsage {
+                slot_bytes: 16 * slot_size,
+                migrating_bytes: 0,
+                owned_bytes: 0,
+            }
+        );
+        for i in 0..1000 {
+            ht.insert(i, i);
+        }
+        assert_eq!(ht.capacity(), 2048);
+        assert_eq!(ht.memory_usage().total(), 2048 * slot_size);
+        for i in 10..1000 {
+            ht.remove(&i);
+        }
+        // removing leaves the allocation alone until the table is rebuilt
+        assert_eq!(ht.memory_usage().slot_bytes, 2048 * slot_size);
+        ht.shrink_to_fit();
+        assert_eq!(ht.capacity(), 16);
+        assert_eq!(ht.memory_usage().slot_bytes, 16 * slot_size);
+
+        let mut ht = SimpleHashTable::<i64, String, SimpleHasher>::builder()
+            .capacity(16)
+            .migration_batch(1)
+            .build()
+            .unwrap();
+        while !ht.is_migrating() {
+            let i = ht.size() as i64;
+            ht.insert(i, "x".repeat(i as usize));
+        }
+        let string_slot = std::mem::size_of::<Slot<i64, String>>();
+        let usage = ht.memory_usage_with(|_, v| v.capacity());
+        assert_eq!(usage.slot_bytes, 32 * string_slot);
+        assert_eq!(usage.migrating_bytes, 16 * string_slot);
+        assert_eq!(usage.owned_bytes, (0..ht.size()).sum::<usize>());
+        assert_eq!(ht.memory_usage().owned_bytes, 0);
+    }
+
+    thread_local! {
+        static PROBE_PASSES: Cell<usize> = const { Cell::new(0) };
+    }
+
+    pub(super) fn count_probe_pass() {
+        PROBE_PASSES.with(|count| count.set(count.get() + 1));
+    }
+
+    fn probe_passes() -> usize {
+        PROBE_PASSES.with(|count| count.replace(0))
+    }
+
+    thread_local! {
+        static MIGRATED_ENTRIES: Cell<usize> = const { Cell::new(0) };
+    }
+
+    pub(super) fn count_migrated_entry() {
+        MIGRATED_ENTRIES.with(|count| count.set(count.get() + 1));
+    }
+
+    fn migrated_entries() -> usize {
+        MIGRATED_ENTRIES.with(|count| count.replace(0))
+    }
+
+    #[test]
+    fn insert_probes_once_test() {
+        let mut ht = SimpleHashTable::<i64, i64, ConstantHasher>::builder()
+            .capacity(64)
+            .tombstone_threshold(1.0)
+            .build()
+            .unwrap();
+        for i in 0..20 {
+            ht.insert(i, i);
+        }
+        for i in 0..10 {
+            ht.remove(&i);
+        }
+        let resizes = ht.resize_count();
+        probe_passes();
+        for i in 100..105 {
+            assert_eq!(ht.insert(i, i), None);
+        }
+        assert_eq!(probe_passes(), 5);
+        // the existence probe passed the tombstones at the front of the cluster
+        // and the fresh keys went straight into them
+        assert_eq!(ht.deleted_slots, 5);
+        assert_eq!(ht.get_pos_mut(&100), Some(0));
+        assert_eq!(ht.insert(100, 0), Some(100));
+        assert_eq!(probe_passes(), 2);
+        assert_eq!(ht.resize_count(), resizes);
+
+        // once a resize intervenes the remembered slot is stale, so the
+        // entry is placed with a fresh probe of the new array
+        let mut ht: IntegerToIntegerHT = create_simple_hash_table(16);
+        for i in 0..12 {
+            ht.insert(i, i);
+        }
+        probe_passes();
+        ht.insert(12, 12);
+        assert_eq!(ht.resize_count(), 1);
+        assert!(probe_passes() >= 2);
+        assert_eq!(ht.debug_validate(), Ok(()));
+        for i in 0..13 {
+            assert_eq!(ht.get(&i), Some(&i));
         }
     }
 }
@@ -0,0 +1,397 @@
+use crate::{
+    grown_capacity, HashTable, HashValue, HashableKey, Hasher, InvariantViolation, KVPair,
+    DEFAULT_CAPACITY, DEFAULT_GROWTH_FACTOR, DEFAULT_MAX_LOAD_FACTOR,
+};
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+
+// Linear probing with backward-shift deletion: removing an entry pulls the rest
+// of its cluster back over the hole, so there are never any tombstones.
+pub struct SimpleHashTableNoTombstones<K: HashableKey, V: HashValue, H: Hasher<K>> {
+    data: Vec<Option<KVPair<K, V>>>,
+    slots_used: usize,
+    resize_count: usize,
+    ph_1: PhantomData<H>,
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> SimpleHashTableNoTombstones<K, V, H> {
+    fn home(&self, key: &K) -> usize {
+        H::hash(key) % self.data.len()
+    }
+
+    fn get_pos<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let capacity = self.data.len();
+        if capacity == 0 {
+            return None;
+        }
+        let start = H::hash(key) % capacity;
+        for step in 0..capacity {
+            let pos = (start + step) % capacity;
+            match &self.data[pos] {
+                Some(kv) if kv.key.borrow() == key => return Some(pos),
+                Some(_) => {}
+                None => return None,
+            }
+        }
+        None
+    }
+
+    fn get_free_pos(&self, key: &K) -> usize {
+        let capacity = self.data.len();
+        let start = self.home(key);
+        (0..capacity)
+            .map(|step| (start + step) % capacity)
+            .find(|&pos| self.data[pos].is_none())
+            .expect("no free slot available after growing")
+    }
+
+    fn max_slots(capacity: usize) -> usize {
+        (capacity as f64 * DEFAULT_MAX_LOAD_FACTOR) as usize
+    }
+
+    fn grow_if_needed(&mut self) {
+        let current_capacity = self.data.len();
+        if self.slots_used < Self::max_slots(current_capacity) {
+            return;
+        }
+        let next_capacity =
+            grown_capacity(current_capacity, DEFAULT_GROWTH_FACTOR).unwrap_or_else(|| {
+                panic!(
+                    "couldn't resize from {}: capacity overflow",
+                    current_capacity
+                )
+            });
+        self.resize(next_capacity);
+    }
+
+    fn resize(&mut self, next_capacity: usize) {
+        let mut new_data = Vec::with_capacity(next_capacity);
+        new_data.resize_with(next_capacity, || None);
+        let old_data = std::mem::replace(&mut self.data, new_data);
+        for kv in old_data.into_iter().flatten() {
+            let pos = self.get_free_pos(&kv.key);
+            self.data[pos] = Some(kv);
+        }
+        self.resize_count += 1;
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(pos) = self.get_pos(&key) {
+            let kv = self.data[pos].as_mut().unwrap();
+            return Some(std::mem::replace(&mut kv.value, value));
+        }
+        self.grow_if_needed();
+        let pos = self.get_free_pos(&key);
+        self.data[pos] = Some(KVPair { key, value });
+        self.slots_used += 1;
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.data[pos].as_ref().map(|kv| &kv.value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.data[pos].as_mut().map(|kv| &mut kv.value)
+    }
+
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.get_pos(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        let kv = self.data[pos].take().unwrap();
+        self.slots_used -= 1;
+        self.shift_back(pos);
+        Some(kv.value)
+    }
+
+    // Knuth's Algorithm R: walk the cluster after `hole` and move back every
+    // entry whose home slot doesn't lie cyclically in (hole, pos].
+    fn shift_back(&mut self, mut hole: usize) {
+        let capacity = self.data.len();
+        let mut pos = hole;
+        loop {
+            pos = (pos + 1) % capacity;
+            let home = match &self.data[pos] {
+                Some(kv) => self.home(&kv.key),
+                None => return,
+            };
+            let stays = if hole <= pos {
+                hole < home && home <= pos
+            } else {
+                hole < home || home <= pos
+            };
+            if !stays {
+                self.data[hole] = self.data[pos].take();
+                hole = pos;
+            }
+        }
+    }
+
+    pub fn iter(&self) -> NoTombstonesIter<'_, K, V> {
+        NoTombstonesIter {
+            slots: self.data.iter(),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn resize_count(&self) -> usize {
+        self.resize_count
+    }
+
+    pub fn probe_distance<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let capacity = self.data.len();
+        let pos = self.get_pos(key)?;
+        Some((pos + capacity - H::hash(key) % capacity) % capacity)
+    }
+
+    // Every key must be reachable from its home slot without crossing an empty
+    // slot, or backward-shift deletion left a hole behind.
+    pub fn debug_validate(&self) -> Result<(), InvariantViolation> {
+        let capacity = self.data.len();
+        let mut occupied = 0;
+        for (index, slot) in self.data.iter().enumerate() {
+            if let Some(kv) = slot {
+                occupied += 1;
+                let mut probe = self.home(&kv.key);
+                while probe != index {
+                    if self.data[probe].is_none() {
+                        return Err(InvariantViolation::UnreachableKey { index });
+                    }
+                    probe = (probe + 1) % capacity;
+                }
+            }
+        }
+        if occupied != self.slots_used {
+            return Err(InvariantViolation::SlotsUsedMismatch {
+                recorded: self.slots_used,
+                actual: occupied,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> HashTable<K, V>
+    for SimpleHashTableNoTombstones<K, V, H>
+{
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        SimpleHashTableNoTombstones::insert(self, key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        SimpleHashTableNoTombstones::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        SimpleHashTableNoTombstones::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        SimpleHashTableNoTombstones::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        SimpleHashTableNoTombstones::remove(self, key)
+    }
+
+    fn clear(&mut self) {
+        for element in self.data.iter_mut() {
+            element.take();
+        }
+        self.slots_used = 0;
+    }
+
+    fn size(&self) -> usize {
+        self.slots_used
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        0
+    }
+}
+
+pub struct NoTombstonesIter<'a, K: HashableKey, V: HashValue> {
+    slots: std::slice::Iter<'a, Option<KVPair<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for NoTombstonesIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kv = self.slots.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((&kv.key, &kv.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for NoTombstonesIter<'_, K, V> {}
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug, H: Hasher<K>> fmt::Debug
+    for SimpleHashTableNoTombstones<K, V, H>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> Default for SimpleHashTableNoTombstones<K, V, H> {
+    fn default() -> Self {
+        create_no_tombstones_hash_table(DEFAULT_CAPACITY)
+    }
+}
+
+pub fn create_no_tombstones_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>>(
+    capacity: usize,
+) -> SimpleHashTableNoTombstones<K, V, H> {
+    let mut data = Vec::with_capacity(capacity);
+    data.resize_with(capacity, || None);
+    SimpleHashTableNoTombstones {
+        data,
+        slots_used: 0,
+        resize_count: 0,
+        ph_1: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdentityHasher, SimpleHasher};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_works() {
+        let mut ht: SimpleHashTableNoTombstones<i64, i64, SimpleHasher> =
+            create_no_tombstones_hash_table(8);
+        assert_eq!(ht.insert(1, 10), None);
+        assert_eq!(ht.insert(1, 11), Some(10));
+        assert_eq!(ht.size(), 1);
+        assert_eq!(ht.get(&1), Some(&11));
+        assert_eq!(ht.remove(&1), Some(11));
+        assert_eq!(ht.remove(&1), None);
+        assert!(ht.is_empty());
+        assert_eq!(ht.wasted_capacity(), 0);
+    }
+
+    #[test]
+    fn shift_back_inside_cluster_test() {
+        let mut ht: SimpleHashTableNoTombstones<i64, i64, IdentityHasher> =
+            create_no_tombstones_hash_table(16);
+        // 0, 16 and 32 share home 0; 1 sits behind them at slot 3
+        for key in [0, 16, 32, 1] {
+            ht.insert(key, key);
+        }
+        assert_eq!(ht.probe_distance(&1), Some(2));
+        ht.remove(&16);
+        assert_eq!(ht.probe_distance(&32), Some(1));
+        assert_eq!(ht.probe_distance(&1), Some(1));
+        ht.remove(&0);
+        assert_eq!(ht.probe_distance(&32), Some(0));
+        assert_eq!(ht.probe_distance(&1), Some(0));
+        assert!(ht.data[2].is_none() && ht.data[3].is_none());
+        assert_eq!(ht.debug_validate(), Ok(()));
+    }
+
+    #[test]
+    fn wrap_around_cluster_test() {
+        let mut ht: SimpleHashTableNoTombstones<i64, i64, IdentityHasher> =
+            create_no_tombstones_hash_table(16);
+        // homes 14 and 15 spill over the end into slots 0..3
+        for key in [14, 30, 46, 15, 31, 0] {
+            ht.insert(key, key);
+        }
+        assert_eq!(ht.probe_distance(&0), Some(3));
+        ht.remove(&14);
+        assert_eq!(ht.debug_validate(), Ok(()));
+        assert_eq!(ht.probe_distance(&30), Some(0));
+        assert_eq!(ht.probe_distance(&0), Some(2));
+        ht.remove(&15);
+        ht.remove(&46);
+        assert_eq!(ht.debug_validate(), Ok(()));
+        for key in [30, 31, 0] {
+            assert_eq!(ht.get(&key), Some(&key));
+        }
+        assert_eq!(ht.probe_distance(&0), Some(0));
+    }
+
+    #[test]
+    fn debug_validate_catches_hole_test() {
+        let mut ht: SimpleHashTableNoTombstones<i64, i64, IdentityHasher> =
+            create_no_tombstones_hash_table(8);
+        ht.insert(0, 0);
+        ht.insert(8, 8);
+        ht.data[0] = None;
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::UnreachableKey { index: 1 })
+        );
+    }
+
+    #[test]
+    fn mixed_hasher_against_model_test() {
+        let mut rng = StdRng::seed_from_u64(81);
+        let mut ht: SimpleHashTableNoTombstones<i64, i64, SimpleHasher> = Default::default();
+        let mut model = HashMap::new();
+        for step in 0..50_000 {
+            let key = rng.gen_range(0..500);
+            match rng.gen_range(0..3) {
+                0 => assert_eq!(ht.remove(&key), model.remove(&key)),
+                1 => assert_eq!(ht.insert(key, step), model.insert(key, step)),
+                _ => assert_eq!(ht.get(&key), model.get(&key)),
+            }
+        }
+        assert_eq!(ht.debug_validate(), Ok(()));
+        let mut entries: Vec<_> = ht.iter().map(|(k, v)| (*k, *v)).collect();
+        entries.sort_unstable();
+        let mut expected: Vec<_> = model.into_iter().collect();
+        expected.sort_unstable();
+        assert_eq!(entries, expected);
+    }
+}
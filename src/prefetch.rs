@@ -0,0 +1,29 @@
+// Hints that `ptr` will be read soon, so the probe that gets there finds it in
+// cache. A prefetch never faults, so `ptr` doesn't have to be valid. Without
+// the `prefetch` feature, or on other targets, this does nothing.
+#[inline(always)]
+pub(crate) fn prefetch_read<T>(ptr: *const T) {
+    #[cfg(all(feature = "prefetch", target_arch = "x86_64", target_feature = "sse"))]
+    // SAFETY: sse is enabled, and prefetching doesn't dereference the pointer.
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr as *const i8);
+    }
+    #[cfg(all(feature = "prefetch", target_arch = "aarch64"))]
+    // SAFETY: prfm only hints the cache and can't fault or write anything.
+    unsafe {
+        core::arch::asm!(
+            "prfm pldl1keep, [{ptr}]",
+            ptr = in(reg) ptr,
+            options(nostack, readonly, preserves_flags)
+        );
+    }
+    #[cfg(not(all(
+        feature = "prefetch",
+        any(
+            all(target_arch = "x86_64", target_feature = "sse"),
+            target_arch = "aarch64"
+        )
+    )))]
+    let _ = ptr;
+}
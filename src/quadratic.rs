@@ -0,0 +1,399 @@
+use crate::{
+    rehash_capacity, HashTable, HashValue, HashableKey, Hasher, InvariantViolation, KVPair,
+    DEFAULT_CAPACITY, DEFAULT_MAX_LOAD_FACTOR,
+};
+use bit_set::BitSet;
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+
+// Open addressing with triangular-number probing: the i-th probe lands at
+// home + i(i+1)/2. With a power-of-two capacity that sequence visits every slot
+// exactly once, so the probes are bounded by the capacity just like the linear
+// table. Removal leaves tombstones, which a rehash clears out.
+pub struct QuadraticHashTable<K: HashableKey, V: HashValue, H: Hasher<K>> {
+    data: Vec<Option<KVPair<K, V>>>,
+    deleted: BitSet,
+    slots_used: usize,
+    deleted_slots: usize,
+    resize_count: usize,
+    ph_1: PhantomData<H>,
+}
+
+enum Probe {
+    Found(usize, usize),
+    Vacant(Option<usize>),
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> QuadraticHashTable<K, V, H> {
+    fn mask(&self) -> usize {
+        self.data.len() - 1
+    }
+
+    // Returns the slot holding a match and how many probes it took, or the
+    // first tombstone or empty slot the key could go in.
+    fn probe<F: Fn(&K) -> bool>(&self, hash: usize, is_match: F) -> Probe {
+        let capacity = self.data.len();
+        if capacity == 0 {
+            return Probe::Vacant(None);
+        }
+        let mut reusable = None;
+        let mut pos = hash & self.mask();
+        for step in 0..capacity {
+            match &self.data[pos] {
+                Some(kv) if is_match(&kv.key) => return Probe::Found(pos, step),
+                Some(_) => {}
+                None if self.deleted.contains(pos) => {
+                    reusable.get_or_insert(pos);
+                }
+                None => return Probe::Vacant(reusable.or(Some(pos))),
+            }
+            pos = (pos + step + 1) & self.mask();
+        }
+        Probe::Vacant(reusable)
+    }
+
+    fn get_pos<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        match self.probe(H::hash(key), |k| k.borrow() == key) {
+            Probe::Found(pos, _) => Some(pos),
+            Probe::Vacant(_) => None,
+        }
+    }
+
+    fn max_slots(capacity: usize) -> usize {
+        (capacity as f64 * DEFAULT_MAX_LOAD_FACTOR) as usize
+    }
+
+    fn resize(&mut self, next_capacity: usize) {
+        let mut new_data = Vec::with_capacity(next_capacity);
+        new_data.resize_with(next_capacity, || None);
+        let old_data = std::mem::replace(&mut self.data, new_data);
+        self.deleted = BitSet::new();
+        self.deleted_slots = 0;
+        for kv in old_data.into_iter().flatten() {
+            match self.probe(H::hash(&kv.key), |_| false) {
+                Probe::Vacant(Some(pos)) => self.data[pos] = Some(kv),
+                _ => panic!("resized table has room for every entry"),
+            }
+        }
+        self.resize_count += 1;
+    }
+
+    // Returns whether the table was rehashed, which moves every entry.
+    fn grow_if_needed(&mut self) -> bool {
+        let capacity = self.data.len();
+        let max_slots = Self::max_slots(capacity);
+        match rehash_capacity(capacity, max_slots, self.slots_used, self.deleted_slots) {
+            Some(next_capacity) => {
+                self.resize(next_capacity);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = H::hash(&key);
+        let vacant = match self.probe(hash, |k| *k == key) {
+            Probe::Found(pos, _) => {
+                let kv = self.data[pos].as_mut().unwrap();
+                return Some(std::mem::replace(&mut kv.value, value));
+            }
+            Probe::Vacant(vacant) => vacant,
+        };
+        let pos = match vacant {
+            Some(pos) if !self.grow_if_needed() => pos,
+            _ => match self.probe(hash, |_| false) {
+                Probe::Vacant(Some(pos)) => pos,
+                _ => panic!("no free slot available after growing"),
+            },
+        };
+        if self.deleted.remove(pos) {
+            self.deleted_slots -= 1;
+        }
+        self.data[pos] = Some(KVPair { key, value });
+        self.slots_used += 1;
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.data[pos].as_ref().map(|kv| &kv.value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.data[pos].as_mut().map(|kv| &mut kv.value)
+    }
+
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.get_pos(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.deleted.insert(pos);
+        self.deleted_slots += 1;
+        self.slots_used -= 1;
+        self.data[pos].take().map(|kv| kv.value)
+    }
+
+    pub fn iter(&self) -> QuadraticIter<'_, K, V> {
+        QuadraticIter {
+            slots: self.data.iter(),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn resize_count(&self) -> usize {
+        self.resize_count
+    }
+
+    // Mean number of extra probes a successful lookup of each entry takes.
+    pub fn average_probe_length(&self) -> f64 {
+        if self.slots_used == 0 {
+            return 0.0;
+        }
+        let total: usize = self
+            .data
+            .iter()
+            .flatten()
+            .map(
+                |kv| match self.probe(H::hash(&kv.key), |k| std::ptr::eq(k, &kv.key)) {
+                    Probe::Found(_, steps) => steps,
+                    Probe::Vacant(_) => unreachable!("every entry is reachable"),
+                },
+            )
+            .sum();
+        total as f64 / self.slots_used as f64
+    }
+
+    pub fn debug_validate(&self) -> Result<(), InvariantViolation> {
+        let mut occupied = 0;
+        for (index, slot) in self.data.iter().enumerate() {
+            if let Some(kv) = slot {
+                occupied += 1;
+                let found = self.probe(H::hash(&kv.key), |k| std::ptr::eq(k, &kv.key));
+                if !matches!(found, Probe::Found(pos, _) if pos == index) {
+                    return Err(InvariantViolation::UnreachableKey { index });
+                }
+            }
+        }
+        if occupied != self.slots_used {
+            return Err(InvariantViolation::SlotsUsedMismatch {
+                recorded: self.slots_used,
+                actual: occupied,
+            });
+        }
+        if self.deleted.len() != self.deleted_slots {
+            return Err(InvariantViolation::DeletedSlotsMismatch {
+                recorded: self.deleted_slots,
+                actual: self.deleted.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> HashTable<K, V> for QuadraticHashTable<K, V, H> {
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        QuadraticHashTable::insert(self, key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        QuadraticHashTable::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        QuadraticHashTable::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        QuadraticHashTable::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        QuadraticHashTable::remove(self, key)
+    }
+
+    fn clear(&mut self) {
+        for element in self.data.iter_mut() {
+            element.take();
+        }
+        self.deleted.clear();
+        self.slots_used = 0;
+        self.deleted_slots = 0;
+    }
+
+    fn size(&self) -> usize {
+        self.slots_used
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        self.deleted_slots
+    }
+}
+
+pub struct QuadraticIter<'a, K: HashableKey, V: HashValue> {
+    slots: std::slice::Iter<'a, Option<KVPair<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for QuadraticIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kv = self.slots.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((&kv.key, &kv.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for QuadraticIter<'_, K, V> {}
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug, H: Hasher<K>> fmt::Debug
+    for QuadraticHashTable<K, V, H>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> Default for QuadraticHashTable<K, V, H> {
+    fn default() -> Self {
+        create_quadratic_hash_table(DEFAULT_CAPACITY)
+    }
+}
+
+// The capacity is rounded up to a power of two.
+pub fn create_quadratic_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>>(
+    capacity: usize,
+) -> QuadraticHashTable<K, V, H> {
+    let capacity = capacity.max(1).next_power_of_two();
+    let mut data = Vec::with_capacity(capacity);
+    data.resize_with(capacity, || None);
+    QuadraticHashTable {
+        data,
+        deleted: BitSet::new(),
+        slots_used: 0,
+        deleted_slots: 0,
+        resize_count: 0,
+        ph_1: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_simple_hash_table, IdentityHasher, SimpleHashTable};
+    use std::collections::HashSet;
+
+    #[test]
+    fn visits_every_slot_test() {
+        for capacity in [1, 2, 8, 64, 1024] {
+            let mut visited = HashSet::new();
+            let mut pos = 3 % capacity;
+            for step in 0..capacity {
+                visited.insert(pos);
+                pos = (pos + step + 1) & (capacity - 1);
+            }
+            assert_eq!(visited.len(), capacity);
+        }
+    }
+
+    #[test]
+    fn power_of_two_capacity_test() {
+        let mut ht: QuadraticHashTable<i64, i64, IdentityHasher> = create_quadratic_hash_table(10);
+        assert_eq!(ht.capacity(), 16);
+        for i in 0..1000 {
+            ht.insert(i, i);
+        }
+        assert!(ht.capacity().is_power_of_two());
+        let ht: QuadraticHashTable<i64, i64, IdentityHasher> = create_quadratic_hash_table(0);
+        assert_eq!(ht.capacity(), 1);
+    }
+
+    #[test]
+    fn dense_sequential_keys_test() {
+        let mut ht: QuadraticHashTable<i64, i64, IdentityHasher> = create_quadratic_hash_table(4);
+        for i in 0..10_000 {
+            assert_eq!(ht.insert(i, -i), None);
+        }
+        for i in (0..10_000).step_by(2) {
+            assert_eq!(ht.remove(&i), Some(-i));
+        }
+        for i in 0..10_000 {
+            assert_eq!(
+                ht.get(&i).copied(),
+                if i % 2 == 0 { None } else { Some(-i) }
+            );
+        }
+        assert_eq!(ht.size(), 5000);
+        assert_eq!(ht.wasted_capacity(), 5000);
+    }
+
+    #[test]
+    fn average_probe_length_test() {
+        let mut quadratic: QuadraticHashTable<i64, i64, IdentityHasher> =
+            create_quadratic_hash_table(4);
+        let mut linear: SimpleHashTable<i64, i64, IdentityHasher> = create_simple_hash_table(4);
+        for i in 0..10_000 {
+            quadratic.insert(i, i);
+            linear.insert(i, i);
+        }
+        // dense keys below a power-of-two capacity each get a home of their own
+        assert_eq!(quadratic.average_probe_length(), 0.0);
+        let linear_average = linear.probe_stats().mean;
+        assert!(
+            quadratic.average_probe_length() + 0.1 < linear_average,
+            "quadratic {} vs linear {}",
+            quadratic.average_probe_length(),
+            linear_average
+        );
+    }
+
+    #[test]
+    fn debug_validate_catches_missing_tombstone_test() {
+        let mut ht: QuadraticHashTable<i64, i64, IdentityHasher> = create_quadratic_hash_table(16);
+        ht.insert(0, 0);
+        ht.insert(16, 16);
+        ht.data[0] = None;
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::UnreachableKey { index: 1 })
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use crate::{
+    grown_capacity, valid_max_load_factor, HashTable, HashValue, HashableKey, Hasher,
+    InvariantViolation, KVPair, DEFAULT_CAPACITY, DEFAULT_GROWTH_FACTOR, DEFAULT_MAX_LOAD_FACTOR,
+};
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+
+// Linear probing where an inserted entry takes the slot of any incumbent that is
+// closer to its home than the newcomer, which keeps probe distances even.
+// Removal shifts the rest of the cluster back, so there are no tombstones.
+pub struct RobinHoodHashTable<K: HashableKey, V: HashValue, H: Hasher<K>> {
+    data: Vec<Option<KVPair<K, V>>>,
+    slots_used: usize,
+    resize_count: usize,
+    max_load_factor: f64,
+    ph_1: PhantomData<H>,
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> RobinHoodHashTable<K, V, H> {
+    pub fn with_max_load_factor(capacity: usize, max_load_factor: f64) -> Self {
+        assert!(
+            valid_max_load_factor(max_load_factor),
+            "max load factor must be in (0, 1), got {}",
+            max_load_factor
+        );
+        let mut data = Vec::with_capacity(capacity);
+        data.resize_with(capacity, || None);
+        RobinHoodHashTable {
+            data,
+            slots_used: 0,
+            resize_count: 0,
+            max_load_factor,
+            ph_1: PhantomData,
+        }
+    }
+
+    fn distance_at(&self, pos: usize) -> Option<usize> {
+        let capacity = self.data.len();
+        self.data[pos]
+            .as_ref()
+            .map(|kv| (pos + capacity - H::hash(&kv.key) % capacity) % capacity)
+    }
+
+    fn get_pos<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let capacity = self.data.len();
+        if capacity == 0 {
+            return None;
+        }
+        let start = H::hash(key) % capacity;
+        for distance in 0..capacity {
+            let pos = (start + distance) % capacity;
+            // an incumbent closer to home than we are means the key would have
+            // displaced it, so it can't be further along
+            match self.distance_at(pos) {
+                Some(incumbent) if incumbent < distance => return None,
+                Some(_) => {
+                    if self.data[pos].as_ref().unwrap().key.borrow() == key {
+                        return Some(pos);
+                    }
+                }
+                None => return None,
+            }
+        }
+        None
+    }
+
+    fn max_slots(&self, capacity: usize) -> usize {
+        (capacity as f64 * self.max_load_factor) as usize
+    }
+
+    fn grow_if_needed(&mut self) {
+        let current_capacity = self.data.len();
+        if self.slots_used < self.max_slots(current_capacity) {
+            return;
+        }
+        let next_capacity =
+            grown_capacity(current_capacity, DEFAULT_GROWTH_FACTOR).unwrap_or_else(|| {
+                panic!(
+                    "couldn't resize from {}: capacity overflow",
+                    current_capacity
+                )
+            });
+        let mut new_data = Vec::with_capacity(next_capacity);
+        new_data.resize_with(next_capacity, || None);
+        let old_data = std::mem::replace(&mut self.data, new_data);
+        for kv in old_data.into_iter().flatten() {
+            self.place(kv);
+        }
+        self.resize_count += 1;
+    }
+
+    // Walks from the entry's home, swapping it with richer incumbents until the
+    // entry being carried lands in an empty slot.
+    fn place(&mut self, kv: KVPair<K, V>) {
+        let capacity = self.data.len();
+        let mut carried = kv;
+        let mut pos = H::hash(&carried.key) % capacity;
+        let mut distance = 0;
+        for _ in 0..capacity {
+            match self.distance_at(pos) {
+                None => {
+                    self.data[pos] = Some(carried);
+                    return;
+                }
+                Some(incumbent) if incumbent < distance => {
+                    carried = self.data[pos].replace(carried).unwrap();
+                    distance = incumbent;
+                }
+                Some(_) => {}
+            }
+            pos = (pos + 1) % capacity;
+            distance += 1;
+        }
+        panic!("no free slot available after growing");
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(pos) = self.get_pos(&key) {
+            let kv = self.data[pos].as_mut().unwrap();
+            return Some(std::mem::replace(&mut kv.value, value));
+        }
+        self.grow_if_needed();
+        self.place(KVPair { key, value });
+        self.slots_used += 1;
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.data[pos].as_ref().map(|kv| &kv.value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.data[pos].as_mut().map(|kv| &mut kv.value)
+    }
+
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.get_pos(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        let kv = self.data[pos].take().unwrap();
+        self.slots_used -= 1;
+        let capacity = self.data.len();
+        let mut hole = pos;
+        loop {
+            let next = (hole + 1) % capacity;
+            match self.distance_at(next) {
+                Some(distance) if distance > 0 => {
+                    self.data[hole] = self.data[next].take();
+                    hole = next;
+                }
+                _ => break,
+            }
+        }
+        Some(kv.value)
+    }
+
+    pub fn iter(&self) -> RobinHoodIter<'_, K, V> {
+        RobinHoodIter {
+            slots: self.data.iter(),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn resize_count(&self) -> usize {
+        self.resize_count
+    }
+
+    pub fn max_load_factor(&self) -> f64 {
+        self.max_load_factor
+    }
+
+    pub fn max_probe_distance(&self) -> usize {
+        (0..self.data.len())
+            .filter_map(|pos| self.distance_at(pos))
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Distances never grow by more than one from a slot to the next, which is
+    // what lets get_pos stop at the first incumbent closer to home.
+    pub fn debug_validate(&self) -> Result<(), InvariantViolation> {
+        let capacity = self.data.len();
+        for index in 0..capacity {
+            if let Some(distance) = self.distance_at(index) {
+                let previous = (index + capacity - 1) % capacity;
+                if distance > self.distance_at(previous).map_or(0, |d| d + 1) {
+                    return Err(InvariantViolation::ProbeOrder { index });
+                }
+            }
+        }
+        let occupied = self.data.iter().flatten().count();
+        if occupied != self.slots_used {
+            return Err(InvariantViolation::SlotsUsedMismatch {
+                recorded: self.slots_used,
+                actual: occupied,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> HashTable<K, V> for RobinHoodHashTable<K, V, H> {
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        RobinHoodHashTable::insert(self, key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        RobinHoodHashTable::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        RobinHoodHashTable::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        RobinHoodHashTable::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        RobinHoodHashTable::remove(self, key)
+    }
+
+    fn clear(&mut self) {
+        for element in self.data.iter_mut() {
+            element.take();
+        }
+        self.slots_used = 0;
+    }
+
+    fn size(&self) -> usize {
+        self.slots_used
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        0
+    }
+}
+
+pub struct RobinHoodIter<'a, K: HashableKey, V: HashValue> {
+    slots: std::slice::Iter<'a, Option<KVPair<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for RobinHoodIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kv = self.slots.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((&kv.key, &kv.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for RobinHoodIter<'_, K, V> {}
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug, H: Hasher<K>> fmt::Debug
+    for RobinHoodHashTable<K, V, H>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> Default for RobinHoodHashTable<K, V, H> {
+    fn default() -> Self {
+        create_robin_hood_hash_table(DEFAULT_CAPACITY)
+    }
+}
+
+pub fn create_robin_hood_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>>(
+    capacity: usize,
+) -> RobinHoodHashTable<K, V, H> {
+    RobinHoodHashTable::with_max_load_factor(capacity, DEFAULT_MAX_LOAD_FACTOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdentityHasher, SimpleHashTable, SimpleHasher};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn it_works() {
+        let mut ht: RobinHoodHashTable<i64, i64, SimpleHasher> = create_robin_hood_hash_table(8);
+        for i in 0..100 {
+            assert_eq!(ht.insert(i, i), None);
+        }
+        assert_eq!(ht.insert(5, 50), Some(5));
+        assert_eq!(ht.size(), 100);
+        for i in 0..100 {
+            assert_eq!(ht.remove(&i), Some(if i == 5 { 50 } else { i }));
+        }
+        assert!(ht.is_empty());
+        assert!(ht.resize_count() > 0);
+    }
+
+    #[test]
+    fn displaces_richer_entries_test() {
+        let mut ht: RobinHoodHashTable<i64, i64, IdentityHasher> = create_robin_hood_hash_table(16);
+        // 1 sits at its home until 0 and 16 push past it
+        ht.insert(1, 1);
+        ht.insert(0, 0);
+        ht.insert(16, 16);
+        assert_eq!(ht.distance_at(1), Some(1));
+        assert_eq!(ht.distance_at(2), Some(1));
+        assert_eq!(ht.debug_validate(), Ok(()));
+        ht.remove(&0);
+        assert_eq!(ht.distance_at(0), Some(0));
+        assert_eq!(ht.distance_at(1), Some(0));
+        assert!(ht.data[2].is_none());
+        assert_eq!(ht.debug_validate(), Ok(()));
+    }
+
+    #[test]
+    fn debug_validate_catches_misordering_test() {
+        let mut ht: RobinHoodHashTable<i64, i64, IdentityHasher> = create_robin_hood_hash_table(16);
+        ht.insert(0, 0);
+        ht.insert(16, 16);
+        ht.data[0] = None;
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::ProbeOrder { index: 1 })
+        );
+    }
+
+    #[test]
+    fn max_probe_distance_test() {
+        let capacity = 4096;
+        let entries = capacity * 9 / 10 - 1;
+        let mut rng = StdRng::seed_from_u64(82);
+        let keys: Vec<i64> = (0..entries).map(|_| rng.gen_range(0..1 << 40)).collect();
+
+        let mut robin_hood: RobinHoodHashTable<i64, i64, IdentityHasher> =
+            RobinHoodHashTable::with_max_load_factor(capacity, 0.9);
+        let mut linear = SimpleHashTable::<i64, i64, IdentityHasher>::builder()
+            .capacity(capacity)
+            .max_load_factor(0.9)
+            .build()
+            .unwrap();
+        for &key in keys.iter() {
+            robin_hood.insert(key, key);
+            linear.insert(key, key);
+        }
+        assert_eq!(robin_hood.capacity(), capacity);
+        assert_eq!(linear.capacity(), capacity);
+
+        let linear_max = linear
+            .slots()
+            .filter_map(|slot| slot.probe_distance)
+            .max()
+            .unwrap();
+        let robin_hood_max = robin_hood.max_probe_distance();
+        assert!(
+            robin_hood_max * 2 < linear_max,
+            "robin hood {} vs linear {}",
+            robin_hood_max,
+            linear_max
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{create_simple_hash_table, HashTable, HashableKey, Hasher, SimpleHashTable};
+use std::borrow::Borrow;
+use std::fmt;
+
+pub struct SimpleHashSet<K: HashableKey, H: Hasher<K>> {
+    table: SimpleHashTable<K, (), H>,
+}
+
+impl<K: HashableKey, H: Hasher<K>> SimpleHashSet<K, H> {
+    pub fn insert(&mut self, key: K) -> bool {
+        self.table.insert(key, ()).is_none()
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.table.has(key)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.table.remove(key).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    pub fn wasted_capacity(&self) -> usize {
+        self.table.wasted_capacity()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.table.reserve(additional);
+    }
+
+    pub fn iter(&self) -> SetIter<'_, K> {
+        SetIter {
+            inner: self.table.keys(),
+        }
+    }
+}
+
+pub struct SetIter<'a, K: HashableKey> {
+    inner: crate::Keys<'a, K, ()>,
+}
+
+impl<'a, K: HashableKey> Iterator for SetIter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: HashableKey> ExactSizeIterator for SetIter<'_, K> {}
+
+impl<K: HashableKey> Clone for SetIter<'_, K> {
+    fn clone(&self) -> Self {
+        SetIter {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, K: HashableKey, H: Hasher<K>> IntoIterator for &'a SimpleHashSet<K, H> {
+    type Item = &'a K;
+    type IntoIter = SetIter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct SetIntoIter<K: HashableKey> {
+    inner: crate::IntoKeys<K, ()>,
+}
+
+impl<K: HashableKey> Iterator for SetIntoIter<K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: HashableKey> ExactSizeIterator for SetIntoIter<K> {}
+
+impl<K: HashableKey, H: Hasher<K>> IntoIterator for SimpleHashSet<K, H> {
+    type Item = K;
+    type IntoIter = SetIntoIter<K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SetIntoIter {
+            inner: self.table.into_keys(),
+        }
+    }
+}
+
+impl<K: HashableKey + Clone, H: Hasher<K>> Clone for SimpleHashSet<K, H> {
+    fn clone(&self) -> Self {
+        SimpleHashSet {
+            table: self.table.clone(),
+        }
+    }
+}
+
+impl<K: HashableKey, H: Hasher<K>> PartialEq for SimpleHashSet<K, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.table == other.table
+    }
+}
+
+impl<K: HashableKey + fmt::Debug, H: Hasher<K>> fmt::Debug for SimpleHashSet<K, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, H: Hasher<K>> Default for SimpleHashSet<K, H> {
+    fn default() -> Self {
+        SimpleHashSet {
+            table: SimpleHashTable::default(),
+        }
+    }
+}
+
+impl<K: HashableKey, H: Hasher<K>> Extend<K> for SimpleHashSet<K, H> {
+    fn extend<T: IntoIterator<Item = K>>(&mut self, iter: T) {
+        self.table
+            .insert_many(iter.into_iter().map(|key| (key, ())));
+    }
+}
+
+impl<K: HashableKey, H: Hasher<K>> FromIterator<K> for SimpleHashSet<K, H> {
+    fn from_iter<T: IntoIterator<Item = K>>(iter: T) -> Self {
+        SimpleHashSet {
+            table: iter.into_iter().map(|key| (key, ())).collect(),
+        }
+    }
+}
+
+pub fn create_simple_hash_set<K: HashableKey, H: Hasher<K>>(
+    capacity: usize,
+) -> SimpleHashSet<K, H> {
+    SimpleHashSet {
+        table: create_simple_hash_table(capacity),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimpleHasher;
+    use rand::Rng;
+    use std::collections::HashSet;
+
+    fn create_integer_set() -> SimpleHashSet<i64, SimpleHasher> {
+        create_simple_hash_set(32)
+    }
+
+    #[test]
+    fn it_works() {
+        let mut set = create_integer_set();
+        assert_eq!(set.len(), 0);
+        assert!(set.insert(25));
+        assert_eq!(set.len(), 1);
+        assert!(!set.insert(25));
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(&25));
+        assert!(set.insert(26));
+        assert_eq!(set.len(), 2);
+        assert!(set.remove(&26));
+        assert!(!set.remove(&26));
+        assert_eq!(set.len(), 1);
+        assert!(!set.contains(&26));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![&25]);
+    }
+
+    #[test]
+    fn overflow_test() {
+        let mut set = create_integer_set();
+        let sz_check: i64 = 1000000;
+        for i in 0..sz_check {
+            set.insert(i);
+            assert_eq!(set.len(), (i + 1) as usize);
+        }
+        assert_eq!(set.len(), sz_check as usize);
+        assert_eq!(set.iter().count(), sz_check as usize);
+    }
+
+    #[test]
+    fn overflow_delete_test() {
+        let mut set = create_integer_set();
+        let sz_check: i64 = 1000000;
+        for i in 0..sz_check {
+            set.insert(i);
+            set.remove(&i);
+            assert_eq!(set.len(), 0);
+        }
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn overflow_random_test() {
+        let mut set = create_integer_set();
+        let sz_check: i64 = 1000;
+        let mut hset: HashSet<i64> = HashSet::new();
+
+        for i in 0..sz_check {
+            let mut num: i64;
+            loop {
+                num = rand::thread_rng().gen_range(0..1_000_000_000_000);
+                if hset.insert(num) {
+                    break;
+                }
+            }
+            set.insert(num);
+            for item in hset.iter() {
+                assert!(set.contains(item));
+            }
+            assert_eq!(set.len(), (i + 1) as usize);
+        }
+        let collected: HashSet<i64> = set.into_iter().collect();
+        assert_eq!(collected, hset);
+    }
+
+    #[test]
+    fn collect_and_extend_test() {
+        let mut set: SimpleHashSet<i64, SimpleHasher> = (0..100).filter(|i| i % 2 == 0).collect();
+        assert_eq!(set.len(), 50);
+        set.extend(0..100);
+        assert_eq!(set.len(), 100);
+        let other: SimpleHashSet<i64, SimpleHasher> = (0..100).rev().collect();
+        assert!(set == other);
+    }
+}
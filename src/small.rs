@@ -0,0 +1,376 @@
+use crate::{
+    HashTable, HashValue, HashableKey, Hasher, InvariantViolation, Iter, KVPair, SimpleHashTable,
+};
+use std::borrow::Borrow;
+use std::fmt;
+
+// Up to N entries live inline, packed at the front of `entries` and found by
+// comparing keys in order, so a table that stays small never allocates or
+// hashes. The insert that would make it N + 1 moves everything into a regular
+// SimpleHashTable, which it then keeps even if entries are removed.
+pub struct SmallHashTable<K: HashableKey, V: HashValue, H: Hasher<K>, const N: usize> {
+    storage: Storage<K, V, H, N>,
+}
+
+enum Storage<K: HashableKey, V: HashValue, H: Hasher<K>, const N: usize> {
+    Inline {
+        entries: [Option<KVPair<K, V>>; N],
+        len: usize,
+    },
+    Spilled(SimpleHashTable<K, V, H>),
+}
+
+fn empty_inline<K: HashableKey, V: HashValue, H: Hasher<K>, const N: usize>() -> Storage<K, V, H, N>
+{
+    Storage::Inline {
+        entries: std::array::from_fn(|_| None),
+        len: 0,
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>, const N: usize> SmallHashTable<K, V, H, N> {
+    fn inline_pos<Q>(entries: &[Option<KVPair<K, V>>], key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        entries
+            .iter()
+            .position(|kv| kv.as_ref().unwrap().key.borrow() == key)
+    }
+
+    fn spill(&mut self) -> &mut SimpleHashTable<K, V, H> {
+        if let Storage::Inline { entries, len } = &mut self.storage {
+            let mut table = SimpleHashTable::for_entries(2 * N);
+            for kv in entries[..*len].iter_mut() {
+                let kv = kv.take().unwrap();
+                table.insert(kv.key, kv.value);
+            }
+            self.storage = Storage::Spilled(table);
+        }
+        match &mut self.storage {
+            Storage::Spilled(table) => table,
+            Storage::Inline { .. } => unreachable!(),
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match &mut self.storage {
+            Storage::Inline { entries, len } => {
+                if let Some(pos) = Self::inline_pos(&entries[..*len], &key) {
+                    let kv = entries[pos].as_mut().unwrap();
+                    return Some(std::mem::replace(&mut kv.value, value));
+                }
+                if *len < N {
+                    entries[*len] = Some(KVPair { key, value });
+                    *len += 1;
+                    return None;
+                }
+                self.spill().insert(key, value)
+            }
+            Storage::Spilled(table) => table.insert(key, value),
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        match &self.storage {
+            Storage::Inline { entries, len } => {
+                let pos = Self::inline_pos(&entries[..*len], key)?;
+                entries[pos].as_ref().map(|kv| &kv.value)
+            }
+            Storage::Spilled(table) => table.get(key),
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        match &mut self.storage {
+            Storage::Inline { entries, len } => {
+                let pos = Self::inline_pos(&entries[..*len], key)?;
+                entries[pos].as_mut().map(|kv| &mut kv.value)
+            }
+            Storage::Spilled(table) => table.get_mut(key),
+        }
+    }
+
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        match &mut self.storage {
+            Storage::Inline { entries, len } => {
+                let pos = Self::inline_pos(&entries[..*len], key)?;
+                // keep the entries packed by moving the last one into the hole
+                *len -= 1;
+                entries.swap(pos, *len);
+                entries[*len].take().map(|kv| kv.value)
+            }
+            Storage::Spilled(table) => table.remove(key),
+        }
+    }
+
+    pub fn iter(&self) -> SmallIter<'_, K, V> {
+        match &self.storage {
+            Storage::Inline { entries, len } => SmallIter {
+                inline: entries[..*len].iter(),
+                spilled: None,
+                remaining: *len,
+            },
+            Storage::Spilled(table) => SmallIter {
+                inline: [].iter(),
+                spilled: Some(table.iter()),
+                remaining: table.size(),
+            },
+        }
+    }
+
+    pub fn is_inline(&self) -> bool {
+        matches!(self.storage, Storage::Inline { .. })
+    }
+
+    // Spilling out of the inline array counts as the first resize.
+    pub fn resize_count(&self) -> usize {
+        match &self.storage {
+            Storage::Inline { .. } => 0,
+            Storage::Spilled(table) => 1 + table.resize_count(),
+        }
+    }
+
+    // Inline entries must stay packed: lookups only scan the first `len`.
+    pub fn debug_validate(&self) -> Result<(), InvariantViolation> {
+        match &self.storage {
+            Storage::Inline { entries, len } => {
+                if let Some(index) = (*len..N).find(|&index| entries[index].is_some()) {
+                    return Err(InvariantViolation::UnreachableKey { index });
+                }
+                let occupied = entries[..*len].iter().flatten().count();
+                if occupied != *len {
+                    return Err(InvariantViolation::SlotsUsedMismatch {
+                        recorded: *len,
+                        actual: occupied,
+                    });
+                }
+                Ok(())
+            }
+            Storage::Spilled(table) => table.debug_validate(),
+        }
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>, const N: usize> HashTable<K, V>
+    for SmallHashTable<K, V, H, N>
+{
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        SmallHashTable::insert(self, key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        SmallHashTable::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        SmallHashTable::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        SmallHashTable::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        SmallHashTable::remove(self, key)
+    }
+
+    // Goes back to the inline array, freeing the spilled table.
+    fn clear(&mut self) {
+        self.storage = empty_inline();
+    }
+
+    fn size(&self) -> usize {
+        match &self.storage {
+            Storage::Inline { len, .. } => *len,
+            Storage::Spilled(table) => table.size(),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        match &self.storage {
+            Storage::Inline { .. } => N,
+            Storage::Spilled(table) => table.capacity(),
+        }
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        match &self.storage {
+            Storage::Inline { .. } => 0,
+            Storage::Spilled(table) => table.wasted_capacity(),
+        }
+    }
+}
+
+pub struct SmallIter<'a, K: HashableKey, V: HashValue> {
+    inline: std::slice::Iter<'a, Option<KVPair<K, V>>>,
+    spilled: Option<Iter<'a, K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for SmallIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = match self.inline.next() {
+            Some(kv) => kv.as_ref().map(|kv| (&kv.key, &kv.value)),
+            None => self.spilled.as_mut()?.next(),
+        }?;
+        self.remaining -= 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for SmallIter<'_, K, V> {}
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug, H: Hasher<K>, const N: usize>
+    fmt::Debug for SmallHashTable<K, V, H, N>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>, const N: usize> Default
+    for SmallHashTable<K, V, H, N>
+{
+    fn default() -> Self {
+        create_small_hash_table()
+    }
+}
+
+// The inline capacity is the type's N; nothing is allocated until it's exceeded.
+pub fn create_small_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>, const N: usize>(
+) -> SmallHashTable<K, V, H, N> {
+    SmallHashTable {
+        storage: empty_inline(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdentityHasher, SimpleHasher};
+    use std::collections::HashMap;
+
+    struct PanickingHasher;
+    impl Hasher<i64> for PanickingHasher {
+        fn hash(_: &i64) -> usize {
+            panic!("inline lookups don't hash");
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        let mut ht: SmallHashTable<i64, i64, SimpleHasher, 8> = create_small_hash_table();
+        for i in 0..1000 {
+            assert_eq!(ht.insert(i, i), None);
+        }
+        assert_eq!(ht.insert(9, 90), Some(9));
+        assert_eq!(ht.size(), 1000);
+        assert!(!ht.is_inline());
+        for i in 0..1000 {
+            assert_eq!(ht.remove(&i), Some(if i == 9 { 90 } else { i }));
+        }
+        assert!(ht.is_empty());
+        assert_eq!(ht.iter().count(), 0);
+        ht.clear();
+        assert!(ht.is_inline());
+        assert_eq!(ht.capacity(), 8);
+    }
+
+    #[test]
+    fn inline_below_threshold_test() {
+        let mut ht: SmallHashTable<i64, i64, PanickingHasher, 4> = create_small_hash_table();
+        for i in 0..4 {
+            assert_eq!(ht.insert(i, i * 10), None);
+        }
+        assert_eq!(ht.insert(2, 21), Some(20));
+        *ht.get_mut(&3).unwrap() += 1;
+        assert_eq!(ht.get(&3), Some(&31));
+        assert!(!ht.has(&4));
+        let entries: HashMap<i64, i64> = ht.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(entries, HashMap::from([(0, 0), (1, 10), (2, 21), (3, 31)]));
+        assert_eq!((ht.capacity(), ht.wasted_capacity()), (4, 0));
+        assert_eq!(ht.resize_count(), 0);
+        assert!(ht.is_inline());
+    }
+
+    #[test]
+    fn spill_transition_test() {
+        let mut ht: SmallHashTable<i64, i64, IdentityHasher, 8> = create_small_hash_table();
+        for i in 0..8 {
+            ht.insert(i, i);
+        }
+        // deleting before the spill keeps the inline entries packed
+        assert_eq!(ht.remove(&0), Some(0));
+        assert_eq!(ht.remove(&0), None);
+        ht.insert(8, 8);
+        assert!(ht.is_inline());
+        assert_eq!(ht.iter().len(), 8);
+
+        ht.insert(9, 9);
+        assert!(!ht.is_inline());
+        assert_eq!(ht.resize_count(), 1);
+        assert_eq!(ht.size(), 9);
+        for i in 1..10 {
+            assert_eq!(ht.get(&i), Some(&i));
+        }
+        assert!(!ht.has(&0));
+
+        // and after it
+        assert_eq!(ht.remove(&5), Some(5));
+        assert!(!ht.has(&5));
+        assert_eq!(ht.insert(5, 50), None);
+        assert_eq!(ht.get(&5), Some(&50));
+        for i in 1..10 {
+            ht.remove(&i);
+        }
+        assert!(ht.is_empty());
+        assert!(!ht.is_inline());
+        assert_eq!(ht.iter().count(), 0);
+    }
+
+    #[test]
+    fn debug_validate_catches_unpacked_entries_test() {
+        let mut ht: SmallHashTable<i64, i64, IdentityHasher, 4> = create_small_hash_table();
+        ht.insert(1, 1);
+        ht.insert(2, 2);
+        if let Storage::Inline { entries, .. } = &mut ht.storage {
+            entries.swap(1, 2);
+        }
+        assert_eq!(
+            ht.debug_validate(),
+            Err(InvariantViolation::UnreachableKey { index: 2 })
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use crate::{
+    rehash_capacity, CapacityPolicy, HashTable, HashValue, HashableKey, Hasher, DEFAULT_CAPACITY,
+    DEFAULT_MAX_LOAD_FACTOR,
+};
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+
+// Everything a probe needs to look at for one slot; the value lives elsewhere.
+enum KeySlot<K> {
+    Empty,
+    Tombstone,
+    Occupied { hash: usize, key: K },
+}
+
+// The same linear probing as SimpleHashTable (power-of-two capacity, scrambled
+// home slot, cached hashes, tombstones), but stored as a structure of arrays:
+// probes scan only `keys`, and `values[i]` belongs to `keys[i]`.
+pub struct SoaHashTable<K: HashableKey, V: HashValue, H: Hasher<K>> {
+    keys: Vec<KeySlot<K>>,
+    values: Vec<Option<V>>,
+    slots_used: usize,
+    deleted_slots: usize,
+    resize_count: usize,
+    ph_1: PhantomData<H>,
+}
+
+enum Probe {
+    Found(usize),
+    Vacant(Option<usize>),
+}
+
+const POLICY: CapacityPolicy = CapacityPolicy::NextPowerOfTwo;
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> SoaHashTable<K, V, H> {
+    // Returns the slot holding a match, or the first tombstone or empty slot
+    // the key could go in.
+    fn probe<F: Fn(&K) -> bool>(&self, hash: usize, is_match: F) -> Probe {
+        let capacity = self.keys.len();
+        let mut reusable = None;
+        let mut pos = POLICY.home_slot(hash, capacity);
+        for _ in 0..capacity {
+            match &self.keys[pos] {
+                KeySlot::Occupied { hash: h, key } if *h == hash && is_match(key) => {
+                    return Probe::Found(pos)
+                }
+                KeySlot::Occupied { .. } => {}
+                KeySlot::Tombstone => {
+                    reusable.get_or_insert(pos);
+                }
+                KeySlot::Empty => return Probe::Vacant(reusable.or(Some(pos))),
+            }
+            pos = POLICY.wrap(pos + 1, capacity);
+        }
+        Probe::Vacant(reusable)
+    }
+
+    fn get_pos<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        match self.probe(H::hash(key), |k| k.borrow() == key) {
+            Probe::Found(pos) => Some(pos),
+            Probe::Vacant(_) => None,
+        }
+    }
+
+    fn max_slots(capacity: usize) -> usize {
+        (capacity as f64 * DEFAULT_MAX_LOAD_FACTOR) as usize
+    }
+
+    fn resize(&mut self, next_capacity: usize) {
+        let mut keys = Vec::with_capacity(next_capacity);
+        keys.resize_with(next_capacity, || KeySlot::Empty);
+        let mut values = Vec::with_capacity(next_capacity);
+        values.resize_with(next_capacity, || None);
+        let old_keys = std::mem::replace(&mut self.keys, keys);
+        let old_values = std::mem::replace(&mut self.values, values);
+        self.deleted_slots = 0;
+        for (slot, value) in old_keys.into_iter().zip(old_values) {
+            let KeySlot::Occupied { hash, key } = slot else {
+                continue;
+            };
+            match self.probe(hash, |_| false) {
+                Probe::Vacant(Some(pos)) => {
+                    self.keys[pos] = KeySlot::Occupied { hash, key };
+                    self.values[pos] = value;
+                }
+                _ => panic!("resized table has room for every entry"),
+            }
+        }
+        self.resize_count += 1;
+    }
+
+    // Returns whether the table was rehashed, which moves every entry.
+    fn grow_if_needed(&mut self) -> bool {
+        let capacity = self.keys.len();
+        let max_slots = Self::max_slots(capacity);
+        match rehash_capacity(capacity, max_slots, self.slots_used, self.deleted_slots) {
+            Some(next_capacity) => {
+                self.resize(next_capacity);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = H::hash(&key);
+        let vacant = match self.probe(hash, |k| *k == key) {
+            Probe::Found(pos) => return self.values[pos].replace(value),
+            Probe::Vacant(vacant) => vacant,
+        };
+        let pos = match vacant {
+            Some(pos) if !self.grow_if_needed() => pos,
+            _ => match self.probe(hash, |_| false) {
+                Probe::Vacant(Some(pos)) => pos,
+                _ => panic!("no free slot available after growing"),
+            },
+        };
+        if let KeySlot::Tombstone = self.keys[pos] {
+            self.deleted_slots -= 1;
+        }
+        self.keys[pos] = KeySlot::Occupied { hash, key };
+        self.values[pos] = Some(value);
+        self.slots_used += 1;
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.values[pos].as_ref()
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.values[pos].as_mut()
+    }
+
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.get_pos(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.keys[pos] = KeySlot::Tombstone;
+        self.deleted_slots += 1;
+        self.slots_used -= 1;
+        self.values[pos].take()
+    }
+
+    pub fn iter(&self) -> SoaIter<'_, K, V> {
+        SoaIter {
+            slots: self.keys.iter().zip(self.values.iter()),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn resize_count(&self) -> usize {
+        self.resize_count
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> HashTable<K, V> for SoaHashTable<K, V, H> {
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        SoaHashTable::insert(self, key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        SoaHashTable::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        SoaHashTable::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        SoaHashTable::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        SoaHashTable::remove(self, key)
+    }
+
+    fn clear(&mut self) {
+        self.keys.fill_with(|| KeySlot::Empty);
+        self.values.fill_with(|| None);
+        self.slots_used = 0;
+        self.deleted_slots = 0;
+    }
+
+    fn size(&self) -> usize {
+        self.slots_used
+    }
+
+    fn capacity(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        self.deleted_slots
+    }
+}
+
+pub struct SoaIter<'a, K: HashableKey, V: HashValue> {
+    slots: std::iter::Zip<std::slice::Iter<'a, KeySlot<K>>, std::slice::Iter<'a, Option<V>>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for SoaIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (slot, value) in self.slots.by_ref() {
+            if let (KeySlot::Occupied { key, .. }, Some(value)) = (slot, value) {
+                self.remaining -= 1;
+                return Some((key, value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for SoaIter<'_, K, V> {}
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug, H: Hasher<K>> fmt::Debug
+    for SoaHashTable<K, V, H>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> Default for SoaHashTable<K, V, H> {
+    fn default() -> Self {
+        create_soa_hash_table(DEFAULT_CAPACITY)
+    }
+}
+
+// The capacity is rounded up to a power of two.
+pub fn create_soa_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>>(
+    capacity: usize,
+) -> SoaHashTable<K, V, H> {
+    let capacity = capacity.max(1).next_power_of_two();
+    let mut keys = Vec::with_capacity(capacity);
+    keys.resize_with(capacity, || KeySlot::Empty);
+    let mut values = Vec::with_capacity(capacity);
+    values.resize_with(capacity, || None);
+    SoaHashTable {
+        keys,
+        values,
+        slots_used: 0,
+        deleted_slots: 0,
+        resize_count: 0,
+        ph_1: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_simple_hash_table, IdentityHasher, SimpleHashTable, SimpleHasher, Slot};
+    use rand::Rng;
+
+    #[test]
+    fn it_works() {
+        let mut ht: SoaHashTable<i64, i64, SimpleHasher> = create_soa_hash_table(0);
+        for i in 0..1000 {
+            assert_eq!(ht.insert(i, i), None);
+        }
+        assert_eq!(ht.insert(9, 90), Some(9));
+        assert_eq!(ht.size(), 1000);
+        assert!(ht.capacity().is_power_of_two());
+        for i in 0..1000 {
+            assert_eq!(ht.remove(&i), Some(if i == 9 { 90 } else { i }));
+        }
+        assert!(ht.is_empty());
+        assert_eq!(ht.iter().count(), 0);
+        assert_eq!(ht.values.iter().flatten().count(), 0);
+    }
+
+    #[test]
+    fn matches_simple_hash_table_test() {
+        let mut rng = rand::thread_rng();
+        let mut soa: SoaHashTable<i64, i64, IdentityHasher> = create_soa_hash_table(1);
+        let mut simple: SimpleHashTable<i64, i64, IdentityHasher> = create_simple_hash_table(1);
+        for step in 0..30_000 {
+            let key = rng.gen_range(0..16) * 64 + rng.gen_range(0..8);
+            match rng.gen_range(0..6) {
+                0 => assert_eq!(soa.remove(&key), simple.remove(&key)),
+                1 | 2 => assert_eq!(soa.insert(key, step), simple.insert(key, step)),
+                3 => {
+                    if let Some(value) = soa.get_mut(&key) {
+                        *value += 1;
+                    }
+                    if let Some(value) = simple.get_mut(&key) {
+                        *value += 1;
+                    }
+                }
+                4 if step % 1000 == 0 => {
+                    soa.clear();
+                    simple.clear();
+                }
+                _ => assert_eq!(soa.get(&key), simple.get(&key)),
+            }
+            assert_eq!(soa.size(), simple.size());
+            assert_eq!(soa.has(&key), simple.has(&key));
+        }
+        let mut entries: Vec<_> = soa.iter().map(|(k, v)| (*k, *v)).collect();
+        entries.sort_unstable();
+        let mut expected: Vec<_> = simple.iter().map(|(k, v)| (*k, *v)).collect();
+        expected.sort_unstable();
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn denser_probe_scans_test() {
+        // Cache-miss proxy: probing touches one stride per step, which is the
+        // key slot here and the whole key-value slot in SimpleHashTable.
+        type Value = [u64; 8];
+        let mut soa: SoaHashTable<i64, Value, SimpleHasher> = create_soa_hash_table(16);
+        let mut simple: SimpleHashTable<i64, Value, SimpleHasher> = create_simple_hash_table(16);
+        for i in 0..5000 {
+            soa.insert(i, [i as u64; 8]);
+            simple.insert(i, [i as u64; 8]);
+        }
+        let steps: usize = simple
+            .slots()
+            .filter_map(|slot| slot.probe_distance)
+            .map(|distance| distance + 1)
+            .sum();
+        let capacity = soa.capacity();
+        let soa_steps: usize = (0..capacity)
+            .filter_map(|pos| match &soa.keys[pos] {
+                KeySlot::Occupied { hash, .. } => {
+                    let home = POLICY.home_slot(*hash, capacity);
+                    Some(POLICY.wrap(pos + capacity - home, capacity) + 1)
+                }
+                _ => None,
+            })
+            .sum();
+        assert_eq!(soa_steps, steps);
+        let soa_bytes = steps * std::mem::size_of::<KeySlot<i64>>();
+        let aos_bytes = steps * std::mem::size_of::<Slot<i64, Value>>();
+        assert!(soa_bytes * 3 < aos_bytes, "{} vs {}", soa_bytes, aos_bytes);
+        for i in 0..5000 {
+            assert_eq!(soa.get(&i), simple.get(&i));
+        }
+    }
+}
@@ -0,0 +1,689 @@
+use crate::prefetch::prefetch_read;
+use crate::{rehash_capacity, HashTable, HashValue, HashableKey, Hasher, KVPair, DEFAULT_CAPACITY};
+use std::borrow::Borrow;
+use std::fmt;
+use std::marker::PhantomData;
+
+const GROUP_WIDTH: usize = 16;
+
+// Control bytes: a full slot stores the top 7 bits of its (mixed) hash with the
+// high bit clear, so EMPTY and DELETED are told apart from full by that bit.
+const EMPTY: u8 = 0b1111_1111;
+const DELETED: u8 = 0b1000_0000;
+
+// Slots in use plus tombstones may fill at most 7/8 of the table.
+const MAX_LOAD_NUMERATOR: usize = 7;
+const MAX_LOAD_DENOMINATOR: usize = 8;
+
+fn h2(hash: usize) -> u8 {
+    // Mix first so weak hashers (e.g. the identity) still spread the fragment.
+    let mixed = (hash as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    (mixed >> 57) as u8
+}
+
+// One bit per slot in a group, lowest bit first.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct BitMask(u16);
+
+impl BitMask {
+    fn any(self) -> bool {
+        self.0 != 0
+    }
+
+    fn lowest(self) -> Option<usize> {
+        if self.any() {
+            Some(self.0.trailing_zeros() as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl Iterator for BitMask {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let bit = self.lowest()?;
+        self.0 &= self.0 - 1;
+        Some(bit)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Group([u8; GROUP_WIDTH]);
+
+impl Group {
+    fn load(ctrl: &[u8]) -> Group {
+        let mut bytes = [0; GROUP_WIDTH];
+        bytes.copy_from_slice(&ctrl[..GROUP_WIDTH]);
+        Group(bytes)
+    }
+
+    fn match_byte(&self, byte: u8) -> BitMask {
+        imp::match_byte(&self.0, byte)
+    }
+
+    fn match_empty(&self) -> BitMask {
+        imp::match_byte(&self.0, EMPTY)
+    }
+
+    fn match_empty_or_deleted(&self) -> BitMask {
+        imp::match_high_bit(&self.0)
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+use sse2 as imp;
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+use neon as imp;
+
+#[cfg(not(any(
+    all(target_arch = "x86_64", target_feature = "sse2"),
+    all(target_arch = "aarch64", target_feature = "neon")
+)))]
+use scalar as imp;
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+mod sse2 {
+    use super::{BitMask, GROUP_WIDTH};
+    use core::arch::x86_64::*;
+
+    pub(super) fn match_byte(group: &[u8; GROUP_WIDTH], byte: u8) -> BitMask {
+        // SAFETY: sse2 is enabled at compile time and `loadu` has no alignment
+        // requirement; the array is exactly 16 bytes.
+        unsafe {
+            let ctrl = _mm_loadu_si128(group.as_ptr() as *const __m128i);
+            let cmp = _mm_cmpeq_epi8(ctrl, _mm_set1_epi8(byte as i8));
+            BitMask(_mm_movemask_epi8(cmp) as u16)
+        }
+    }
+
+    pub(super) fn match_high_bit(group: &[u8; GROUP_WIDTH]) -> BitMask {
+        // SAFETY: as above.
+        unsafe {
+            let ctrl = _mm_loadu_si128(group.as_ptr() as *const __m128i);
+            BitMask(_mm_movemask_epi8(ctrl) as u16)
+        }
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use super::{BitMask, GROUP_WIDTH};
+    use core::arch::aarch64::*;
+
+    const BIT_WEIGHTS: [u8; GROUP_WIDTH] =
+        [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+
+    // NEON has no movemask: keep one weighted bit per matching lane and sum
+    // each half.
+    fn to_bitmask(lanes: uint8x16_t) -> BitMask {
+        // SAFETY: neon is enabled at compile time and the weights are 16 bytes.
+        unsafe {
+            let bits = vandq_u8(lanes, vld1q_u8(BIT_WEIGHTS.as_ptr()));
+            let low = vaddv_u8(vget_low_u8(bits)) as u16;
+            let high = vaddv_u8(vget_high_u8(bits)) as u16;
+            BitMask(low | (high << 8))
+        }
+    }
+
+    pub(super) fn match_byte(group: &[u8; GROUP_WIDTH], byte: u8) -> BitMask {
+        // SAFETY: as above; the group is exactly 16 bytes.
+        unsafe {
+            let ctrl = vld1q_u8(group.as_ptr());
+            to_bitmask(vceqq_u8(ctrl, vdupq_n_u8(byte)))
+        }
+    }
+
+    pub(super) fn match_high_bit(group: &[u8; GROUP_WIDTH]) -> BitMask {
+        // SAFETY: as above.
+        unsafe {
+            let ctrl = vld1q_u8(group.as_ptr());
+            to_bitmask(vtstq_u8(ctrl, vdupq_n_u8(0x80)))
+        }
+    }
+}
+
+// Portable fallback working on the group as two u64 words. The zero-byte test
+// is exact (no false positives), so it agrees bit for bit with the SIMD paths.
+#[cfg(any(
+    test,
+    not(any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "aarch64", target_feature = "neon")
+    ))
+))]
+mod scalar {
+    use super::{BitMask, GROUP_WIDTH};
+
+    const LOW_SEVEN: u64 = 0x7F7F_7F7F_7F7F_7F7F;
+    const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+    // Gathers the high bit of each byte into the low 8 bits.
+    fn high_bits_to_mask(word: u64) -> u16 {
+        ((word >> 7 & 0x0101_0101_0101_0101).wrapping_mul(0x0102_0408_1020_4080) >> 56) as u16
+    }
+
+    fn words(group: &[u8; GROUP_WIDTH]) -> [u64; 2] {
+        let mut low = [0; 8];
+        let mut high = [0; 8];
+        low.copy_from_slice(&group[..8]);
+        high.copy_from_slice(&group[8..]);
+        [u64::from_le_bytes(low), u64::from_le_bytes(high)]
+    }
+
+    fn combine(group: &[u8; GROUP_WIDTH], f: impl Fn(u64) -> u64) -> BitMask {
+        let [low, high] = words(group);
+        BitMask(high_bits_to_mask(f(low)) | (high_bits_to_mask(f(high)) << 8))
+    }
+
+    pub(super) fn match_byte(group: &[u8; GROUP_WIDTH], byte: u8) -> BitMask {
+        let repeated = u64::from_le_bytes([byte; 8]);
+        combine(group, |word| {
+            let x = word ^ repeated;
+            !(((x & LOW_SEVEN) + LOW_SEVEN) | x | LOW_SEVEN)
+        })
+    }
+
+    pub(super) fn match_high_bit(group: &[u8; GROUP_WIDTH]) -> BitMask {
+        combine(group, |word| word & HIGH_BITS)
+    }
+}
+
+// Open addressing in the style of SwissTable: alongside the slots there is one
+// control byte per slot, and lookups scan a whole group of 16 control bytes at
+// once, only comparing keys whose 7-bit hash fragment matches. Groups are
+// probed with triangular steps over a power-of-two number of groups.
+//
+// This is its own table rather than a new layout for SimpleHashTable, whose API
+// is defined slot by slot along a linear probe sequence (probe_iter, slots,
+// probe_stats, cursors and handles) in ways group probing can't keep.
+pub struct SwissHashTable<K: HashableKey, V: HashValue, H: Hasher<K>> {
+    data: Vec<Option<KVPair<K, V>>>,
+    ctrl: Vec<u8>,
+    slots_used: usize,
+    deleted_slots: usize,
+    resize_count: usize,
+    ph_1: PhantomData<H>,
+}
+
+enum Probe {
+    Found(usize),
+    Vacant(Option<usize>),
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> SwissHashTable<K, V, H> {
+    fn group_mask(&self) -> usize {
+        self.ctrl.len() / GROUP_WIDTH - 1
+    }
+
+    // Returns the slot holding a match, or the first empty or deleted slot the
+    // key could go in.
+    fn probe<F: Fn(&K) -> bool>(&self, hash: usize, is_match: F) -> Probe {
+        let fragment = h2(hash);
+        let groups = self.ctrl.len() / GROUP_WIDTH;
+        let mut reusable = None;
+        let mut group_index = hash & self.group_mask();
+        for step in 0..groups {
+            let base = group_index * GROUP_WIDTH;
+            let next_group = (group_index + step + 1) & self.group_mask();
+            prefetch_read(self.ctrl.as_ptr().wrapping_add(next_group * GROUP_WIDTH));
+            let group = Group::load(&self.ctrl[base..]);
+            for bit in group.match_byte(fragment) {
+                let kv = self.data[base + bit].as_ref().unwrap();
+                if is_match(&kv.key) {
+                    return Probe::Found(base + bit);
+                }
+            }
+            if reusable.is_none() {
+                reusable = group
+                    .match_empty_or_deleted()
+                    .lowest()
+                    .map(|bit| base + bit);
+            }
+            if group.match_empty().any() {
+                return Probe::Vacant(reusable);
+            }
+            group_index = next_group;
+        }
+        Probe::Vacant(reusable)
+    }
+
+    fn get_pos<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        match self.probe(H::hash(key), |k| k.borrow() == key) {
+            Probe::Found(pos) => Some(pos),
+            Probe::Vacant(_) => None,
+        }
+    }
+
+    fn max_slots(capacity: usize) -> usize {
+        capacity / MAX_LOAD_DENOMINATOR * MAX_LOAD_NUMERATOR
+    }
+
+    fn set_ctrl(&mut self, pos: usize, byte: u8) {
+        if self.ctrl[pos] == DELETED {
+            self.deleted_slots -= 1;
+        }
+        self.ctrl[pos] = byte;
+    }
+
+    fn resize(&mut self, next_capacity: usize) {
+        let mut new_data = Vec::with_capacity(next_capacity);
+        new_data.resize_with(next_capacity, || None);
+        let old_data = std::mem::replace(&mut self.data, new_data);
+        self.ctrl = vec![EMPTY; next_capacity];
+        self.deleted_slots = 0;
+        for kv in old_data.into_iter().flatten() {
+            let hash = H::hash(&kv.key);
+            match self.probe(hash, |_| false) {
+                Probe::Vacant(Some(pos)) => {
+                    self.ctrl[pos] = h2(hash);
+                    self.data[pos] = Some(kv);
+                }
+                _ => panic!("resized table has room for every entry"),
+            }
+        }
+        self.resize_count += 1;
+    }
+
+    // Returns whether the table was rehashed, which moves every entry.
+    fn grow_if_needed(&mut self) -> bool {
+        let capacity = self.data.len();
+        let max_slots = Self::max_slots(capacity);
+        match rehash_capacity(capacity, max_slots, self.slots_used, self.deleted_slots) {
+            Some(next_capacity) => {
+                self.resize(next_capacity);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = H::hash(&key);
+        let vacant = match self.probe(hash, |k| *k == key) {
+            Probe::Found(pos) => {
+                let kv = self.data[pos].as_mut().unwrap();
+                return Some(std::mem::replace(&mut kv.value, value));
+            }
+            Probe::Vacant(vacant) => vacant,
+        };
+        let pos = match vacant {
+            Some(pos) if !self.grow_if_needed() => pos,
+            _ => match self.probe(hash, |_| false) {
+                Probe::Vacant(Some(pos)) => pos,
+                _ => panic!("no free slot available after growing"),
+            },
+        };
+        self.set_ctrl(pos, h2(hash));
+        self.data[pos] = Some(KVPair { key, value });
+        self.slots_used += 1;
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.data[pos].as_ref().map(|kv| &kv.value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        self.data[pos].as_mut().map(|kv| &mut kv.value)
+    }
+
+    pub fn has<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        self.get_pos(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        H: Hasher<Q>,
+    {
+        let pos = self.get_pos(key)?;
+        // Lookups stop at the first group with an empty slot, so none of them
+        // can probe past this group if it already has one; only otherwise does
+        // the slot need a tombstone.
+        let base = pos / GROUP_WIDTH * GROUP_WIDTH;
+        if Group::load(&self.ctrl[base..]).match_empty().any() {
+            self.ctrl[pos] = EMPTY;
+        } else {
+            self.ctrl[pos] = DELETED;
+            self.deleted_slots += 1;
+        }
+        self.slots_used -= 1;
+        self.data[pos].take().map(|kv| kv.value)
+    }
+
+    pub fn iter(&self) -> SwissIter<'_, K, V> {
+        SwissIter {
+            slots: self.data.iter(),
+            remaining: self.slots_used,
+        }
+    }
+
+    pub fn resize_count(&self) -> usize {
+        self.resize_count
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> HashTable<K, V> for SwissHashTable<K, V, H> {
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        SwissHashTable::insert(self, key, value)
+    }
+
+    fn has(&self, key: &K) -> bool {
+        SwissHashTable::has(self, key)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        SwissHashTable::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        SwissHashTable::get_mut(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        SwissHashTable::remove(self, key)
+    }
+
+    fn clear(&mut self) {
+        for element in self.data.iter_mut() {
+            element.take();
+        }
+        self.ctrl.fill(EMPTY);
+        self.slots_used = 0;
+        self.deleted_slots = 0;
+    }
+
+    fn size(&self) -> usize {
+        self.slots_used
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    fn wasted_capacity(&self) -> usize {
+        self.deleted_slots
+    }
+}
+
+pub struct SwissIter<'a, K: HashableKey, V: HashValue> {
+    slots: std::slice::Iter<'a, Option<KVPair<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K: HashableKey, V: HashValue> Iterator for SwissIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kv = self.slots.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((&kv.key, &kv.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: HashableKey, V: HashValue> ExactSizeIterator for SwissIter<'_, K, V> {}
+
+impl<K: HashableKey + fmt::Debug, V: HashValue + fmt::Debug, H: Hasher<K>> fmt::Debug
+    for SwissHashTable<K, V, H>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: HashableKey, V: HashValue, H: Hasher<K>> Default for SwissHashTable<K, V, H> {
+    fn default() -> Self {
+        create_swiss_hash_table(DEFAULT_CAPACITY)
+    }
+}
+
+// The capacity is rounded up to a power of two of at least one group.
+pub fn create_swiss_hash_table<K: HashableKey, V: HashValue, H: Hasher<K>>(
+    capacity: usize,
+) -> SwissHashTable<K, V, H> {
+    let capacity = capacity.max(GROUP_WIDTH).next_power_of_two();
+    let mut data = Vec::with_capacity(capacity);
+    data.resize_with(capacity, || None);
+    SwissHashTable {
+        data,
+        ctrl: vec![EMPTY; capacity],
+        slots_used: 0,
+        deleted_slots: 0,
+        resize_count: 0,
+        ph_1: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_no_tombstones_hash_table, IdentityHasher, SimpleHashTableNoTombstones};
+    use rand::Rng;
+    use std::cell::Cell;
+
+    fn naive_match(group: &[u8; GROUP_WIDTH], is_match: impl Fn(u8) -> bool) -> BitMask {
+        let mut mask = 0;
+        for (i, &byte) in group.iter().enumerate() {
+            if is_match(byte) {
+                mask |= 1 << i;
+            }
+        }
+        BitMask(mask)
+    }
+
+    #[test]
+    fn group_match_paths_agree_test() {
+        let mut rng = rand::thread_rng();
+        let interesting = [EMPTY, DELETED, 0x00, 0x01, 0x7F, 0x80, 0xFE];
+        for _ in 0..5000 {
+            let mut group = [0u8; GROUP_WIDTH];
+            for byte in group.iter_mut() {
+                *byte = if rng.gen_bool(0.5) {
+                    interesting[rng.gen_range(0..interesting.len())]
+                } else {
+                    rng.gen()
+                };
+            }
+            let byte = group[rng.gen_range(0..GROUP_WIDTH)];
+            for probe in [byte, EMPTY, rng.gen_range(0..0x80)] {
+                let expected = naive_match(&group, |b| b == probe);
+                assert_eq!(scalar::match_byte(&group, probe), expected);
+                assert_eq!(imp::match_byte(&group, probe), expected);
+            }
+            let expected = naive_match(&group, |b| b & 0x80 != 0);
+            assert_eq!(scalar::match_high_bit(&group), expected);
+            assert_eq!(imp::match_high_bit(&group), expected);
+        }
+    }
+
+    #[test]
+    fn bitmask_iter_test() {
+        assert_eq!(BitMask(0b1010_0001).collect::<Vec<_>>(), vec![0, 5, 7]);
+        assert_eq!(BitMask(0x8000).lowest(), Some(15));
+        assert_eq!(BitMask(0).next(), None);
+    }
+
+    #[test]
+    fn it_works() {
+        let mut ht: SwissHashTable<i64, i64, IdentityHasher> = create_swiss_hash_table(0);
+        assert_eq!(ht.capacity(), GROUP_WIDTH);
+        for i in 0..1000 {
+            assert_eq!(ht.insert(i, i), None);
+        }
+        assert_eq!(ht.insert(9, 90), Some(9));
+        assert_eq!(ht.size(), 1000);
+        assert!(ht.capacity().is_power_of_two());
+        for i in 0..1000 {
+            assert_eq!(ht.remove(&i), Some(if i == 9 { 90 } else { i }));
+        }
+        assert!(ht.is_empty());
+        assert_eq!(ht.iter().count(), 0);
+    }
+
+    // Full slots hold their key's fragment, the rest are EMPTY or DELETED, and
+    // the counters match.
+    fn assert_control_bytes(ht: &SwissHashTable<i64, i64, IdentityHasher>) {
+        let mut tombstones = 0;
+        for (pos, &ctrl) in ht.ctrl.iter().enumerate() {
+            match &ht.data[pos] {
+                Some(kv) => assert_eq!(ctrl, h2(IdentityHasher::hash(&kv.key))),
+                None if ctrl == DELETED => tombstones += 1,
+                None => assert_eq!(ctrl, EMPTY),
+            }
+        }
+        assert_eq!(tombstones, ht.wasted_capacity());
+        assert_eq!(ht.data.iter().flatten().count(), ht.size());
+    }
+
+    #[test]
+    fn control_bytes_track_slots_test() {
+        let mut ht: SwissHashTable<i64, i64, IdentityHasher> = create_swiss_hash_table(64);
+        for i in 0..40 {
+            ht.insert(i * 64, i);
+        }
+        for i in (0..40).step_by(3) {
+            ht.remove(&(i * 64));
+        }
+        assert_control_bytes(&ht);
+
+        let mut rng = rand::thread_rng();
+        let mut ht: SwissHashTable<i64, i64, IdentityHasher> = create_swiss_hash_table(1);
+        for step in 0..30_000 {
+            let key = rng.gen_range(0..16) * 64 + rng.gen_range(0..8);
+            if rng.gen_bool(0.4) {
+                ht.remove(&key);
+            } else {
+                ht.insert(key, step);
+            }
+            if step % 100 == 0 {
+                assert_control_bytes(&ht);
+            }
+        }
+    }
+
+    #[test]
+    fn probe_wraps_past_last_group_test() {
+        let mut ht: SwissHashTable<i64, i64, IdentityHasher> = create_swiss_hash_table(64);
+        let last = ht.group_mask() as i64;
+        // all of these start in the last group; the next groups probed are 0
+        // and then 2
+        let keys: Vec<i64> = (0..40).map(|i| i * (last + 1) + last).collect();
+        for &key in keys.iter() {
+            ht.insert(key, key);
+        }
+        assert_eq!(ht.capacity(), 64);
+        let group_of = |ht: &SwissHashTable<i64, i64, IdentityHasher>, key: i64| match ht
+            .probe(IdentityHasher::hash(&key), |k| *k == key)
+        {
+            Probe::Found(pos) => pos / GROUP_WIDTH,
+            Probe::Vacant(_) => panic!("{} is missing", key),
+        };
+        let groups: Vec<usize> = keys.iter().map(|&key| group_of(&ht, key)).collect();
+        assert!(groups[..16].iter().all(|&group| group == 3));
+        assert!(groups[16..32].iter().all(|&group| group == 0));
+        assert!(groups[32..].iter().all(|&group| group == 2));
+
+        // the last group stays full of tombstones, so lookups still probe
+        // past it and around to the other groups
+        for &key in keys[..16].iter() {
+            assert_eq!(ht.remove(&key), Some(key));
+        }
+        assert_eq!(ht.wasted_capacity(), 16);
+        for &key in keys[16..].iter() {
+            assert_eq!(group_of(&ht, key), if key < keys[32] { 0 } else { 2 });
+        }
+        assert!(!ht.has(&(40 * (last + 1) + last)));
+        assert_control_bytes(&ht);
+    }
+
+    thread_local! {
+        static KEY_COMPARISONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug)]
+    struct ComparedKey(String);
+
+    impl PartialEq for ComparedKey {
+        fn eq(&self, other: &Self) -> bool {
+            KEY_COMPARISONS.with(|count| count.set(count.get() + 1));
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for ComparedKey {}
+
+    impl HashableKey for ComparedKey {}
+
+    struct StringHasher;
+    impl Hasher<ComparedKey> for StringHasher {
+        fn hash(key: &ComparedKey) -> usize {
+            // FNV-1a
+            key.0.bytes().fold(0xcbf2_9ce4_8422_2325u64, |acc, b| {
+                (acc ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+            }) as usize
+        }
+    }
+
+    fn count_lookup_comparisons<T: HashTable<ComparedKey, usize>>(ht: &mut T) -> usize {
+        for i in 0..3000 {
+            ht.insert(ComparedKey(format!("key-{}", i)), i);
+        }
+        KEY_COMPARISONS.with(|count| count.set(0));
+        for i in 0..6000 {
+            let key = ComparedKey(format!("key-{}", i));
+            assert_eq!(ht.get(&key), if i < 3000 { Some(&i) } else { None });
+        }
+        KEY_COMPARISONS.with(|count| count.get())
+    }
+
+    #[test]
+    fn fewer_key_comparisons_test() {
+        let mut swiss: SwissHashTable<ComparedKey, usize, StringHasher> =
+            create_swiss_hash_table(8);
+        // plain linear probing, comparing keys at every occupied slot
+        let mut linear: SimpleHashTableNoTombstones<ComparedKey, usize, StringHasher> =
+            create_no_tombstones_hash_table(8);
+        let swiss_comparisons = count_lookup_comparisons(&mut swiss);
+        let linear_comparisons = count_lookup_comparisons(&mut linear);
+        // Every hit needs one comparison; beyond that only a full slot whose
+        // fragment collides (about 1 in 128) costs one.
+        assert!(swiss_comparisons >= 3000);
+        assert!(swiss_comparisons < 4000, "{}", swiss_comparisons);
+        assert!(
+            swiss_comparisons * 2 < linear_comparisons,
+            "{} vs {}",
+            swiss_comparisons,
+            linear_comparisons
+        );
+    }
+}
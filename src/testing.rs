@@ -0,0 +1,128 @@
+use crate::{create_simple_hash_table, HashTable, HashValue, HashableKey, Hasher, SimpleHashTable};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op<K, V> {
+    Insert(K, V),
+    Get(K),
+    Delete(K),
+    Clear,
+    Reserve(usize),
+}
+
+// Three bytes per operation: a tag, a key and an argument. Keys come from a
+// single byte so that deletes and re-inserts keep hitting the same clusters.
+pub fn decode_ops(bytes: &[u8]) -> Vec<Op<i64, i64>> {
+    bytes
+        .chunks_exact(3)
+        .map(|chunk| {
+            let key = chunk[1] as i64;
+            let arg = chunk[2];
+            match chunk[0] % 16 {
+                0..=6 => Op::Insert(key, arg as i64),
+                7..=9 => Op::Get(key),
+                10..=13 => Op::Delete(key),
+                14 => Op::Clear,
+                _ => Op::Reserve(arg as usize),
+            }
+        })
+        .collect()
+}
+
+// Applies one operation to any HashTable and to a HashMap model, panicking if
+// their results differ. The trait has no reserve, so Reserve only touches the
+// model here; check_ops reserves on SimpleHashTable itself.
+pub fn apply_op<K, V, T>(table: &mut T, model: &mut HashMap<K, V>, op: &Op<K, V>)
+where
+    K: HashableKey + Hash + Clone + Debug,
+    V: HashValue + Clone + PartialEq + Debug,
+    T: HashTable<K, V>,
+{
+    match op {
+        Op::Insert(key, value) => assert_eq!(
+            table.insert(key.clone(), value.clone()),
+            model.insert(key.clone(), value.clone()),
+            "{:?}",
+            op
+        ),
+        Op::Get(key) => assert_eq!(table.get(key), model.get(key), "{:?}", op),
+        Op::Delete(key) => assert_eq!(table.remove(key), model.remove(key), "{:?}", op),
+        Op::Clear => {
+            table.clear();
+            model.clear();
+        }
+        Op::Reserve(additional) => model.reserve(*additional),
+    }
+}
+
+// Panics unless `table` agrees with `model` on `size`, and on `has` and `get`
+// for every key in `keys`.
+pub fn assert_equivalent<'a, K, V, T, I>(table: &T, model: &HashMap<K, V>, keys: I)
+where
+    K: HashableKey + Hash + Debug + 'a,
+    V: HashValue + PartialEq + Debug,
+    T: HashTable<K, V>,
+    I: IntoIterator<Item = &'a K>,
+{
+    assert_eq!(table.size(), model.len());
+    for key in keys {
+        assert_eq!(table.has(key), model.contains_key(key), "key {:?}", key);
+        assert_eq!(table.get(key), model.get(key), "key {:?}", key);
+    }
+}
+
+// Runs `ops` against a SimpleHashTable and a HashMap, checking after every step
+// that they agree on every byte-sized key and that the table's invariants hold.
+pub fn check_ops<H: Hasher<i64>>(ops: &[Op<i64, i64>]) {
+    let mut table: SimpleHashTable<i64, i64, H> = create_simple_hash_table(4);
+    let mut model: HashMap<i64, i64> = HashMap::new();
+    let keys: Vec<i64> = (0..=u8::MAX as i64).collect();
+    for (step, op) in ops.iter().enumerate() {
+        if let Op::Reserve(additional) = op {
+            table.reserve(*additional);
+        }
+        apply_op(&mut table, &mut model, op);
+        if let Err(violation) = table.debug_validate() {
+            panic!("step {} ({:?}): {}", step, op, violation);
+        }
+        assert_equivalent(&table, &model, &keys);
+    }
+}
+
+pub fn check_bytes<H: Hasher<i64>>(bytes: &[u8]) {
+    check_ops::<H>(&decode_ops(bytes));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdentityHasher, SimpleHasher};
+    use rand::Rng;
+
+    #[test]
+    fn decode_ops_test() {
+        assert_eq!(
+            decode_ops(&[0, 5, 7, 8, 5, 0, 10, 5, 0, 14, 0, 0, 15, 0, 9, 1]),
+            vec![
+                Op::Insert(5, 7),
+                Op::Get(5),
+                Op::Delete(5),
+                Op::Clear,
+                Op::Reserve(9)
+            ]
+        );
+    }
+
+    #[test]
+    fn bounded_random_ops_test() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            let len = rng.gen_range(0..1500);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            check_bytes::<SimpleHasher>(&bytes);
+            check_bytes::<IdentityHasher>(&bytes);
+        }
+    }
+}
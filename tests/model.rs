@@ -0,0 +1,184 @@
+use proptest::prelude::*;
+use rust_ht::testing::{apply_op, assert_equivalent, Op};
+use rust_ht::{
+    create_chained_hash_table, create_cuckoo_hash_table, create_double_hash_table,
+    create_hopscotch_hash_table, create_no_tombstones_hash_table, create_quadratic_hash_table,
+    create_robin_hood_hash_table, create_simple_hash_table, create_small_hash_table,
+    create_soa_hash_table, create_swiss_hash_table, CapacityPolicy, ChainedHashTable,
+    CuckooHashTable, DoubleHashTable, HashTable, Hasher, HopscotchHashTable, IdentityHasher,
+    InvariantViolation, QuadraticHashTable, RobinHoodHashTable, SimpleHashTable,
+    SimpleHashTableNoTombstones, SimpleHasher, SlotState, SmallHashTable, SoaHashTable,
+    SwissHashTable,
+};
+use std::collections::HashMap;
+
+const KEY_RANGE: i64 = 24;
+
+// A narrow key range keeps most keys colliding once the table is small.
+fn op_strategy() -> impl Strategy<Value = Op<i64, i64>> {
+    let key = 0..KEY_RANGE;
+    prop_oneof![
+        4 => (key.clone(), any::<i64>()).prop_map(|(k, v)| Op::Insert(k, v)),
+        2 => key.clone().prop_map(Op::Get),
+        3 => key.prop_map(Op::Delete),
+        1 => Just(Op::Clear),
+        1 => (0..64usize).prop_map(Op::Reserve),
+    ]
+}
+
+fn run_model<H: Hasher<i64>>(mut table: SimpleHashTable<i64, i64, H>, ops: &[Op<i64, i64>]) {
+    let mut model = HashMap::new();
+    let keys: Vec<i64> = (0..KEY_RANGE).collect();
+    for op in ops {
+        if let Op::Reserve(additional) = op {
+            table.reserve(*additional);
+        }
+        apply_op(&mut table, &mut model, op);
+        assert_equivalent(&table, &model, &keys);
+        let mut entries: Vec<(i64, i64)> = table.iter().map(|(k, v)| (*k, *v)).collect();
+        entries.sort_unstable();
+        let mut expected: Vec<(i64, i64)> = model.iter().map(|(k, v)| (*k, *v)).collect();
+        expected.sort_unstable();
+        assert_eq!(entries, expected);
+        assert_eq!(table.debug_validate(), Ok(()));
+    }
+}
+
+// For the other tables, through the HashTable trait plus the length of their
+// own iterator and their invariant checks, which the trait doesn't cover.
+fn run_trait_model<T: HashTable<i64, i64>>(
+    mut table: T,
+    iter_len: impl Fn(&T) -> usize,
+    validate: impl Fn(&T) -> Result<(), InvariantViolation>,
+    ops: &[Op<i64, i64>],
+) {
+    let mut model = HashMap::new();
+    let keys: Vec<i64> = (0..KEY_RANGE).collect();
+    for op in ops {
+        apply_op(&mut table, &mut model, op);
+        assert_equivalent(&table, &model, &keys);
+        assert_eq!(iter_len(&table), model.len());
+        assert_eq!(validate(&table), Ok(()));
+    }
+}
+
+proptest! {
+    #[test]
+    fn simple_hasher_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        run_model::<SimpleHasher>(create_simple_hash_table(4), &ops);
+    }
+
+    #[test]
+    fn identity_hasher_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        run_model::<IdentityHasher>(create_simple_hash_table(4), &ops);
+    }
+
+    #[test]
+    fn prime_capacity_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table = SimpleHashTable::<i64, i64, IdentityHasher>::builder()
+            .capacity(4)
+            .capacity_policy(CapacityPolicy::NextPrime)
+            .build()
+            .unwrap();
+        run_model(table, &ops);
+    }
+
+    #[test]
+    fn incremental_resize_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(4)
+            .migration_batch(2)
+            .build()
+            .unwrap();
+        run_model(table, &ops);
+    }
+
+    #[test]
+    fn auto_shrink_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table = SimpleHashTable::<i64, i64, SimpleHasher>::builder()
+            .capacity(4)
+            .shrink_threshold(0.125)
+            .build()
+            .unwrap();
+        run_model(table, &ops);
+    }
+
+    #[test]
+    fn slot_states_match_counters(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let mut table: SimpleHashTable<i64, i64, IdentityHasher> = create_simple_hash_table(4);
+        let mut model = HashMap::new();
+        for op in &ops {
+            apply_op(&mut table, &mut model, op);
+            let count = |state| table.slots().filter(|slot| slot.state == state).count();
+            prop_assert_eq!(count(SlotState::Occupied), model.len());
+            prop_assert_eq!(count(SlotState::Tombstone), table.wasted_capacity());
+            prop_assert_eq!(
+                count(SlotState::Empty),
+                table.capacity() - model.len() - table.wasted_capacity()
+            );
+        }
+    }
+
+    #[test]
+    fn no_tombstones_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table: SimpleHashTableNoTombstones<i64, i64, IdentityHasher> =
+            create_no_tombstones_hash_table(4);
+        run_trait_model(table, |t| t.iter().len(), |t| t.debug_validate(), &ops);
+    }
+
+    #[test]
+    fn robin_hood_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table: RobinHoodHashTable<i64, i64, IdentityHasher> = create_robin_hood_hash_table(4);
+        run_trait_model(table, |t| t.iter().len(), |t| t.debug_validate(), &ops);
+    }
+
+    #[test]
+    fn quadratic_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table: QuadraticHashTable<i64, i64, IdentityHasher> = create_quadratic_hash_table(4);
+        run_trait_model(table, |t| t.iter().len(), |t| t.debug_validate(), &ops);
+    }
+
+    #[test]
+    fn double_hash_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table: DoubleHashTable<i64, i64, IdentityHasher, SimpleHasher> =
+            create_double_hash_table(4);
+        run_trait_model(table, |t| t.iter().len(), |t| t.debug_validate(), &ops);
+    }
+
+    #[test]
+    fn cuckoo_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table: CuckooHashTable<i64, i64, SimpleHasher, IdentityHasher> =
+            create_cuckoo_hash_table(4);
+        run_trait_model(table, |t| t.iter().len(), |t| t.debug_validate(), &ops);
+    }
+
+    #[test]
+    fn hopscotch_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table: HopscotchHashTable<i64, i64, IdentityHasher> = create_hopscotch_hash_table(4);
+        run_trait_model(table, |t| t.iter().len(), |t| t.debug_validate(), &ops);
+    }
+
+    #[test]
+    fn chained_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table: ChainedHashTable<i64, i64, IdentityHasher> = create_chained_hash_table(4);
+        run_trait_model(table, |t| t.iter().len(), |_| Ok(()), &ops);
+    }
+
+    #[test]
+    fn swiss_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table: SwissHashTable<i64, i64, IdentityHasher> = create_swiss_hash_table(4);
+        run_trait_model(table, |t| t.iter().len(), |_| Ok(()), &ops);
+    }
+
+    #[test]
+    fn small_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table: SmallHashTable<i64, i64, IdentityHasher, 8> = create_small_hash_table();
+        run_trait_model(table, |t| t.iter().len(), |t| t.debug_validate(), &ops);
+    }
+
+    #[test]
+    fn soa_matches_model(ops in prop::collection::vec(op_strategy(), 0..200)) {
+        let table: SoaHashTable<i64, i64, IdentityHasher> = create_soa_hash_table(4);
+        run_trait_model(table, |t| t.iter().len(), |_| Ok(()), &ops);
+    }
+}
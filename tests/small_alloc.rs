@@ -0,0 +1,54 @@
+use rust_ht::{create_small_hash_table, HashTable, SimpleHasher, SmallHashTable};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn small_table_allocates_only_when_it_spills() {
+    let before = allocations();
+    let mut ht: SmallHashTable<i64, i64, SimpleHasher, 8> = create_small_hash_table();
+    for round in 0..100 {
+        for i in 0..8 {
+            ht.insert(i, round);
+        }
+        assert_eq!(ht.get(&7), Some(&round));
+        for i in (0..8).step_by(2) {
+            ht.remove(&i);
+        }
+        assert_eq!(ht.size(), 4);
+        ht.clear();
+    }
+    assert_eq!(allocations(), before);
+
+    for i in 0..9 {
+        ht.insert(i, i);
+    }
+    assert!(!ht.is_inline());
+    assert!(allocations() > before);
+    for i in 0..9 {
+        assert_eq!(ht.get(&i), Some(&i));
+    }
+}